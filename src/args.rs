@@ -0,0 +1,121 @@
+use crate::game::{rotation_system, GameMode, KeyboardLayout, Layout, RotationSystem, Ruleset};
+use std::{env, path::PathBuf};
+
+/// Options passed on the command line
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// `--layout <name>`: override the detected keyboard layout
+    pub layout: Option<KeyboardLayout>,
+    /// `--latency-test`: run the input latency test instead of the game
+    pub latency_test: bool,
+    /// `--input-echo`: show the raw input events instead of playing
+    pub input_echo: bool,
+    /// `--mode <name>`: the game mode to play
+    pub mode: Option<GameMode>,
+    /// `--speed <multiplier>`: the game speed, for modes that allow changing it
+    pub speed: Option<f32>,
+    /// `--broadcast`: use the large layout meant for projecting or streaming
+    pub broadcast: bool,
+    /// `--large-print`: write the score, level and timer in large digits
+    pub large_print: bool,
+    /// `--compact`: use the narrow layout meant for small terminals
+    pub compact: bool,
+    /// `--name <name>`: the player name shown in the broadcast layout
+    pub player_name: Option<String>,
+    /// `--hold-slots <1-3>`: how many pieces can be held at once
+    pub hold_slots: Option<usize>,
+    /// `--rules <name>`: the ruleset preset to play with
+    pub rules: Option<Ruleset>,
+    /// `--rotation <name>`: the rotation system to use instead of the ruleset's
+    pub rotation_system: Option<&'static dyn RotationSystem>,
+    /// `--seed <number>`: deal the same piece sequence every game, in practice mode
+    pub seed: Option<u64>,
+    /// `--reveal-sequence`: list the upcoming pieces on the pause screen, in practice mode
+    pub reveal_sequence: bool,
+    /// `--record <file>`: log every input of a practice game to a text file
+    pub record: Option<PathBuf>,
+    /// `--replay <file>`: play back the inputs logged by `--record`
+    pub replay: Option<PathBuf>,
+    /// `--export-profile <file>`: bundle the config and dig history into a file instead of
+    /// playing
+    pub export_profile: Option<PathBuf>,
+    /// `--import-profile <file>`: unpack a bundled profile instead of playing
+    pub import_profile: Option<PathBuf>,
+    /// `--trace <file>`: record how long each phase of every frame takes to a trace file
+    pub trace: Option<PathBuf>,
+    /// `--relaxed`: cap gravity and extend lock delay for players who need more time
+    pub relaxed: bool,
+    /// `--frenzy`: play with two pieces falling at once
+    pub frenzy: bool,
+    /// `--contamination`: locked cells can turn into cracked cells and bombs
+    pub contamination: bool,
+    /// `--cascade`: floating blocks fall after a clear and can clear more lines
+    pub cascade: bool,
+    /// `--quirks <list>`: turn terminal workarounds on or off, overriding the detected ones
+    pub quirks: Option<String>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut parsed = Self::default();
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--layout" => {
+                    parsed.layout = args
+                        .next()
+                        .and_then(|name| KeyboardLayout::from_name(&name));
+                }
+                "--latency-test" => parsed.latency_test = true,
+                "--input-echo" => parsed.input_echo = true,
+                "--mode" => parsed.mode = args.next().and_then(|name| GameMode::from_name(&name)),
+                "--speed" => parsed.speed = args.next().and_then(|speed| speed.parse().ok()),
+                "--broadcast" => parsed.broadcast = true,
+                "--large-print" => parsed.large_print = true,
+                "--compact" => parsed.compact = true,
+                "--name" => parsed.player_name = args.next(),
+                "--hold-slots" => {
+                    parsed.hold_slots = args
+                        .next()
+                        .and_then(|slots| slots.parse().ok())
+                        .filter(|slots| (1..=3).contains(slots));
+                }
+                "--rules" => parsed.rules = args.next().and_then(|name| Ruleset::from_name(&name)),
+                "--rotation" => {
+                    parsed.rotation_system = args
+                        .next()
+                        .and_then(|name| rotation_system::from_name(&name));
+                }
+                "--seed" => parsed.seed = args.next().and_then(|seed| seed.parse().ok()),
+                "--reveal-sequence" => parsed.reveal_sequence = true,
+                "--record" => parsed.record = args.next().map(PathBuf::from),
+                "--replay" => parsed.replay = args.next().map(PathBuf::from),
+                "--export-profile" => parsed.export_profile = args.next().map(PathBuf::from),
+                "--import-profile" => parsed.import_profile = args.next().map(PathBuf::from),
+                "--relaxed" => parsed.relaxed = true,
+                "--frenzy" => parsed.frenzy = true,
+                "--contamination" => parsed.contamination = true,
+                "--cascade" => parsed.cascade = true,
+                "--quirks" => parsed.quirks = args.next(),
+                "--trace" => parsed.trace = args.next().map(PathBuf::from),
+                _ => (),
+            }
+        }
+
+        parsed
+    }
+
+    pub fn layout(&self) -> Layout {
+        if self.broadcast {
+            Layout::broadcast(self.player_name.clone())
+        } else if self.large_print {
+            Layout::LargePrint
+        } else if self.compact {
+            Layout::Compact
+        } else {
+            Layout::Standard
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    game::{AlertSettings, AssistSettings, BellSettings, DigSettings, GravityStep, InputSettings},
+    sync::SyncSettings,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// The version of the config file layout this build writes. Files without a `version` key are
+/// version 0
+const CONFIG_VERSION: i64 = 1;
+
+/// Upgrades from each config version to the next, starting from version 0. Adding a field with a
+/// default doesn't need one, but renaming, moving or reshaping an existing field does. Each
+/// returns whether it changed anything
+const MIGRATIONS: [fn(&mut DocumentMut) -> bool; CONFIG_VERSION as usize] = [wrap_single_bindings];
+
+/// User settings, read from `config.toml` in the platform's config directory
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    /// Key bindings keyed by action name, replacing the defaults for that action
+    pub bindings: BTreeMap<String, Vec<String>>,
+    pub input: InputSettings,
+    pub bell: BellSettings,
+    pub assists: AssistSettings,
+    pub alerts: AlertSettings,
+    pub dig: DigSettings,
+    /// Rows of a custom gravity curve, replacing the default one if there are any
+    pub gravity: Vec<GravityStep>,
+    /// Piece colours keyed by piece letter, replacing the defaults for that piece
+    pub colours: BTreeMap<String, String>,
+    /// Fill each piece with its own pattern as well as its colour
+    pub piece_patterns: bool,
+    /// Show the game's pace against the average of recent games while playing
+    pub pace_overlay: bool,
+    /// Draw a thin border between pieces in the stack, so each locked piece stays recognisable
+    pub connected_pieces: bool,
+    pub sync: SyncSettings,
+    /// Look for a new release when the game starts, at most once a day
+    #[cfg(feature = "update-check")]
+    pub check_for_updates: bool,
+}
+
+impl Config {
+    /// The directory holding the config file, e.g. `~/.config/console-tetris`
+    pub fn directory() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+
+        base.map(|dir| dir.join("console-tetris"))
+    }
+
+    /// Load the config file, falling back to the defaults if it doesn't exist. Files written for
+    /// an older version are upgraded in place if they need changing, keeping a backup of the
+    /// original
+    ///
+    /// Returns the config along with notices about any upgrade
+    pub fn load() -> Result<(Self, Vec<String>), String> {
+        let Some(path) = Self::directory().map(|dir| dir.join("config.toml")) else {
+            return Ok((Self::default(), Vec::new()));
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Ok((Self::default(), Vec::new()))
+            }
+            Err(error) => return Err(format!("failed to read {}: {error}", path.display())),
+        };
+        let parse_error =
+            |error: &dyn Display| format!("failed to parse {}: {error}", path.display());
+
+        let mut document: DocumentMut = contents.parse().map_err(|error| parse_error(&error))?;
+        let mut notices = Vec::new();
+        if let Some(old_version) = upgrade(&mut document) {
+            notices.push(match save_upgrade(&path, old_version, &document) {
+                Ok(backup) => format!(
+                    "Upgraded {} to version {CONFIG_VERSION}, the original was saved to {}",
+                    path.display(),
+                    backup.display()
+                ),
+                Err(error) => format!(
+                    "Upgraded {} to version {CONFIG_VERSION} for this game only, since it couldn't be saved: {error}",
+                    path.display()
+                ),
+            });
+        }
+
+        let config = toml::from_str(&document.to_string()).map_err(|error| parse_error(&error))?;
+        Ok((config, notices))
+    }
+}
+
+/// Write the bindings into the `[bindings]` section of the config file, keeping the rest of it and
+/// copying the original to `config.toml.bak` first
+///
+/// Returns the path of the config file
+pub fn save_bindings(bindings: &BTreeMap<String, Vec<String>>) -> Result<PathBuf, String> {
+    let path = Config::directory()
+        .map(|dir| dir.join("config.toml"))
+        .ok_or_else(|| String::from("there is no config directory"))?;
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => {
+            fs::copy(&path, path.with_extension("toml.bak")).map_err(|error| error.to_string())?;
+            contents
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            format!("version = {CONFIG_VERSION}\n")
+        }
+        Err(error) => return Err(error.to_string()),
+    };
+    let contents = with_bindings(&contents, bindings)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+    }
+    fs::write(&path, contents).map_err(|error| error.to_string())?;
+
+    Ok(path)
+}
+
+/// Set the bindings in the `[bindings]` table of the config file's contents, leaving everything
+/// else as it was, comments included
+fn with_bindings(
+    contents: &str,
+    bindings: &BTreeMap<String, Vec<String>>,
+) -> Result<String, String> {
+    let mut document: DocumentMut = contents
+        .parse()
+        .map_err(|error: toml_edit::TomlError| error.to_string())?;
+    let Some(section) = document
+        .entry("bindings")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+    else {
+        return Err(String::from("`bindings` isn't a table"));
+    };
+
+    for (action, keys) in bindings {
+        let keys = Value::Array(keys.iter().map(String::as_str).collect());
+        match section.get_mut(action).and_then(Item::as_value_mut) {
+            Some(value) => replace_value(value, keys),
+            None => {
+                section.insert(action, Item::Value(keys));
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Replace a value, keeping any comment next to it
+fn replace_value(value: &mut Value, mut replacement: Value) {
+    *replacement.decor_mut() = value.decor().clone();
+    *value = replacement;
+}
+
+/// Run every migration the config needs to reach the current version
+///
+/// Returns the version it was upgraded from, or `None` if nothing needed changing. Files from
+/// newer versions are left alone, since their new fields are ignored anyway
+fn upgrade(document: &mut DocumentMut) -> Option<i64> {
+    let version = document
+        .get("version")
+        .and_then(Item::as_integer)
+        .unwrap_or(0);
+    if !(0..CONFIG_VERSION).contains(&version) {
+        return None;
+    }
+
+    let mut changed = false;
+    for migration in &MIGRATIONS[version as usize..] {
+        changed |= migration(document);
+    }
+    if !changed {
+        return None;
+    }
+    document["version"] = toml_edit::value(CONFIG_VERSION);
+
+    Some(version)
+}
+
+/// Copy the original file to `config.toml.v<old_version>.bak` and write the upgraded one over it,
+/// keeping its comments and layout
+///
+/// Returns the path of the backup
+fn save_upgrade(path: &Path, old_version: i64, document: &DocumentMut) -> Result<PathBuf, String> {
+    let backup = path.with_extension(format!("toml.v{old_version}.bak"));
+    fs::copy(path, &backup).map_err(|error| error.to_string())?;
+    fs::write(path, document.to_string()).map_err(|error| error.to_string())?;
+
+    Ok(backup)
+}
+
+/// Version 0 to 1: allow bindings written as a single key, e.g. `hold = "c"`, by putting them in
+/// a list
+fn wrap_single_bindings(document: &mut DocumentMut) -> bool {
+    let Some(bindings) = document
+        .get_mut("bindings")
+        .and_then(Item::as_table_like_mut)
+    else {
+        return false;
+    };
+
+    let mut changed = false;
+    for (_, keys) in bindings.iter_mut() {
+        let Some(value) = keys.as_value_mut() else {
+            continue;
+        };
+        let Some(key) = value.as_str() else {
+            continue;
+        };
+
+        let wrapped = Value::Array(Array::from_iter([key]));
+        replace_value(value, wrapped);
+        changed = true;
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgraded(contents: &str) -> Option<String> {
+        let mut document: DocumentMut = contents.parse().expect("Test config isn't valid TOML");
+        upgrade(&mut document).map(|_| document.to_string())
+    }
+
+    #[test]
+    fn single_bindings_are_wrapped_in_lists_keeping_the_layout() {
+        let contents = r#"# My keys
+[bindings]
+hold = "c" # left hand
+hard_drop = ["space"]
+
+[bell]
+enabled = true
+"#;
+        let expected = r#"version = 1
+# My keys
+[bindings]
+hold = ["c"] # left hand
+hard_drop = ["space"]
+
+[bell]
+enabled = true
+"#;
+        assert_eq!(upgraded(contents).as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn configs_that_need_no_changes_are_left_alone() {
+        assert_eq!(upgraded("[bindings]\nhold = [\"c\"]\n"), None);
+        assert_eq!(upgraded("# Nothing set yet\n"), None);
+    }
+
+    #[test]
+    fn saved_bindings_only_change_the_bindings_table() {
+        let contents = r#"version = 1
+# My keys
+[bindings]
+hold = ["space"] # left hand
+pause = ["Esc"]
+
+[bell]
+# Quiet please
+enabled = false
+"#;
+        let bindings = BTreeMap::from([
+            (String::from("hard_drop"), Vec::new()),
+            (String::from("hold"), vec![String::from("c")]),
+        ]);
+        let expected = r#"version = 1
+# My keys
+[bindings]
+hold = ["c"] # left hand
+pause = ["Esc"]
+hard_drop = []
+
+[bell]
+# Quiet please
+enabled = false
+"#;
+        assert_eq!(with_bindings(contents, &bindings).as_deref(), Ok(expected));
+    }
+
+    #[test]
+    fn saved_bindings_get_a_table_if_there_isnt_one() {
+        let bindings = BTreeMap::from([(String::from("pause"), vec![String::from("p")])]);
+        assert_eq!(
+            with_bindings("version = 1\n", &bindings).as_deref(),
+            Ok("version = 1\n\n[bindings]\npause = [\"p\"]\n")
+        );
+    }
+
+    #[test]
+    fn configs_from_newer_versions_are_left_alone() {
+        assert_eq!(upgraded("version = 2\n[bindings]\nhold = \"c\"\n"), None);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::game::Multiplexer;
+use console_input::keypress::exit_raw_mode;
+use crossterm::{
+    cursor,
+    event::{
+        poll, read, DisableFocusChange, EnableFocusChange, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers,
+    },
+    execute,
+};
+use gemini_engine::{
+    ascii::Text,
+    core::{ColChar, Modifier, Vec2D},
+    view::View,
+};
+use std::{
+    collections::VecDeque,
+    io::stdout,
+    time::{Duration, Instant},
+};
+
+const FPS: f32 = 60.0;
+/// How many of the latest events the input echo screen shows
+const ECHO_HISTORY_LENGTH: usize = 16;
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Summarise the samples as their average and percentiles
+fn summarise(name: &str, samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return format!("{name}: no samples");
+    }
+
+    samples.sort_unstable();
+    let average = samples.iter().sum::<Duration>() / samples.len() as u32;
+    let percentile = |p: usize| as_millis(samples[(samples.len() - 1) * p / 100]);
+
+    format!(
+        "{name}: avg {:.1}ms | p50 {:.1}ms | p95 {:.1}ms | p99 {:.1}ms | max {:.1}ms ({} samples)",
+        as_millis(average),
+        percentile(50),
+        percentile(95),
+        percentile(99),
+        percentile(100),
+        samples.len()
+    )
+}
+
+/// Run the input latency test screen until Esc is pressed, then print a report
+///
+/// Key presses are timestamped as soon as they arrive and compared against the moment the frame
+/// responding to them has been processed by the terminal, which is confirmed by requesting the
+/// cursor position. The game renders at the same rate, so this is the lag a player will feel
+pub fn input_latency_test() {
+    let frame_length = Duration::from_secs_f32(1.0 / FPS);
+    let mut view = View::new(50, 5, ColChar::EMPTY);
+
+    let mut pending_presses: Vec<Instant> = Vec::new();
+    let mut input_to_display = Vec::new();
+    let mut terminal_round_trip = Vec::new();
+    let mut last_latency = None;
+
+    'test: loop {
+        let frame_start = Instant::now();
+
+        // Wait for the next frame, timestamping key presses as soon as they arrive
+        while let Some(remaining) = frame_length.checked_sub(frame_start.elapsed()) {
+            if !poll(remaining).expect("Failed to poll input") {
+                break;
+            }
+
+            if let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) = read().expect("Failed to read input")
+            {
+                match code {
+                    KeyCode::Esc => break 'test,
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        exit_raw_mode()
+                    }
+                    _ => pending_presses.push(Instant::now()),
+                }
+            }
+        }
+
+        view.clear();
+        view.draw(&Text::new(
+            Vec2D::new(0, 0),
+            "Input latency test - press any key, Esc to finish",
+            Modifier::None,
+        ));
+        view.draw(&Text::new(
+            Vec2D::new(0, 2),
+            &format!(
+                "Key presses: {}",
+                input_to_display.len() + pending_presses.len()
+            ),
+            Modifier::None,
+        ));
+        if let Some(latency) = last_latency {
+            view.draw(&Text::new(
+                Vec2D::new(0, 3),
+                &format!("Last latency: {:.1}ms", as_millis(latency)),
+                Modifier::None,
+            ));
+        }
+        view.display_render()
+            .expect("Failed to print render to screen");
+
+        if !pending_presses.is_empty() {
+            // The terminal only answers the position request once it has processed the frame
+            let rendered = Instant::now();
+            if cursor::position().is_ok() {
+                terminal_round_trip.push(rendered.elapsed());
+            }
+
+            let displayed = Instant::now();
+            input_to_display.extend(pending_presses.iter().map(|pressed| displayed - *pressed));
+            pending_presses.clear();
+            last_latency = input_to_display.last().copied();
+        }
+    }
+
+    view.clear();
+    view.display_render().expect("Failed to clear screen");
+    println!("{}\r", summarise("Input to display", &mut input_to_display));
+    println!(
+        "{}\r",
+        summarise("Terminal round trip", &mut terminal_round_trip)
+    );
+}
+
+/// Describe an event the way the game receives it
+fn describe_event(event: &Event) -> String {
+    match event {
+        Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind,
+            state,
+        }) => format!("Key {code:?} | {kind:?} | modifiers {modifiers:?} | state {state:?}"),
+        Event::FocusGained => String::from("Focus gained"),
+        Event::FocusLost => String::from("Focus lost"),
+        Event::Resize(width, height) => format!("Resize to {width}x{height}"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Show every event the terminal sends as it arrives, with the time since the previous one, until
+/// Esc is pressed twice in a row. Lets players check what the game actually receives from an
+/// unusual terminal, including key releases, repeats and focus changes if it reports them
+pub fn input_echo() {
+    // Running inside tmux or screen can stop some events reaching the game at all
+    let notes = Multiplexer::detect().map_or_else(Vec::new, Multiplexer::input_notes);
+    let mut view = View::new(100, ECHO_HISTORY_LENGTH + notes.len() + 2, ColChar::EMPTY);
+    let mut history: VecDeque<String> = VecDeque::with_capacity(ECHO_HISTORY_LENGTH);
+    let mut last_event = Instant::now();
+    let mut last_was_esc = false;
+    let _ = execute!(stdout(), EnableFocusChange);
+
+    loop {
+        view.clear();
+        view.draw(&Text::new(
+            Vec2D::new(0, 0),
+            "Input echo - press any key, Esc twice to finish",
+            Modifier::None,
+        ));
+        for (i, note) in notes.iter().enumerate() {
+            view.draw(&Text::new(
+                Vec2D::new(0, i as i64 + 1),
+                &format!("Note: {note}"),
+                Modifier::None,
+            ));
+        }
+        for (i, line) in history.iter().enumerate() {
+            view.draw(&Text::new(
+                Vec2D::new(0, (i + notes.len()) as i64 + 2),
+                line,
+                Modifier::None,
+            ));
+        }
+        view.display_render()
+            .expect("Failed to print render to screen");
+
+        let event = read().expect("Failed to read input");
+        let since_last = last_event.elapsed();
+        last_event = Instant::now();
+
+        let is_esc = matches!(
+            event,
+            Event::Key(KeyEvent {
+                code: KeyCode::Esc,
+                kind: KeyEventKind::Press,
+                ..
+            })
+        );
+        if is_esc && last_was_esc {
+            break;
+        }
+        // Releases and repeats don't count as another key, so Esc's own release doesn't reset it
+        if is_esc {
+            last_was_esc = true;
+        } else if matches!(
+            event,
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
+                ..
+            })
+        ) {
+            last_was_esc = false;
+        }
+
+        if history.len() == ECHO_HISTORY_LENGTH {
+            history.pop_front();
+        }
+        history.push_back(format!(
+            "+{:>7.1}ms  {}",
+            as_millis(since_last),
+            describe_event(&event)
+        ));
+    }
+
+    let _ = execute!(stdout(), DisableFocusChange);
+    view.clear();
+    view.display_render().expect("Failed to clear screen");
+}
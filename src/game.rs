@@ -1,212 +1,1627 @@
-use console_input::keypress as input;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::{
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
+        KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+};
 use gemini_engine::{
-    ascii::{Sprite, Text},
+    ascii::{Sprite, Text, TextAlign},
+    containers::PixelContainer,
     core::{ColChar, Modifier, Vec2D},
     gameloop::MainLoopRoot,
     view::View,
 };
+use rand::{rngs::StdRng, SeedableRng};
 
 mod alerts;
+mod assists;
+mod bell;
+mod big_text;
 mod block_manager;
+mod clock;
 mod collision_manager;
+mod combo_timer;
+mod controls;
+mod dig_history;
+mod garbage;
+mod gravity;
+#[cfg(test)]
+mod guideline;
+mod input_filter;
+mod input_log;
+mod layout;
+mod messages;
+mod mode;
+mod pace_history;
 mod pause;
+mod piece_colours;
+mod preferences;
+mod profiler;
+mod quirks;
+mod render_thread;
+mod results;
+mod rotation_overlay;
+mod rules;
+mod snapshot;
+mod terminal;
+use crate::sync::SyncSettings;
 use alerts::AlertDisplay;
-use block_manager::BlockManager;
-use collision_manager::CollisionManager;
+pub use alerts::AlertSettings;
+pub use assists::AssistSettings;
+use assists::{best_placement_score, find_t_slots, SlowMotion};
+pub use bell::BellSettings;
+use bell::{Bell, BellEvent};
+pub use block_manager::rotation_system::{self, RotationSystem};
+use block_manager::{tetris_core, Block, BlockManager, BlockType, LivePiece};
+use clock::GameClock;
+use collision_manager::{generate_borders, CollisionManager};
+use combo_timer::ComboTimer;
+pub use controls::{Action, BindingProblem, Controls, InputSettings, KeyboardLayout};
+use dig_history::DigRecord;
+pub use garbage::DigSettings;
+pub use gravity::{GravityCurve, GravityStep};
+use input_filter::InputFilter;
+pub use input_log::{InputRecorder, Replay};
+pub use layout::Layout;
+use layout::{Transformed, BROADCAST_BOARD_OFFSET};
+use messages::Message;
+pub use mode::GameMode;
+use pace_history::{Pace, PaceRecord};
 use pause::pause;
+pub use piece_colours::{ColourDepth, PieceColours};
+pub use preferences::Preferences;
+pub use profiler::Profiler;
+use quirks::{draw_cells, Quirked};
+pub use quirks::{Multiplexer, Quirks};
+use render_thread::RenderThread;
+use results::Results;
+use rotation_overlay::{describe_kick, rotation_overlay};
+pub use rules::Ruleset;
+use rules::Scoring;
+use snapshot::{CellSnapshot, GameSnapshot, PieceSnapshot, SNAPSHOT_VERSION};
+use std::{
+    io::stdout,
+    time::{Duration, Instant},
+};
+
+use self::big_text::big_text;
+
+use self::alerts::{generate_alert_for_filled_lines, generate_classic_alert_for_filled_lines};
 
-use self::alerts::generate_alert_for_filled_lines;
+/// Gravity used while soft dropping, unless the level's gravity is already faster
+const SOFT_DROP_GRAVITY: f32 = 0.5;
+const LINES_PER_LEVEL: i64 = 10;
+/// How many upcoming pieces are listed on the pause screen when the sequence is revealed
+const REVEALED_PIECE_COUNT: usize = 70;
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct Game {
     view: View,
+    render_thread: RenderThread,
     alert_display: AlertDisplay,
     block_manager: BlockManager,
     collision_manager: CollisionManager,
+    bell: Bell,
+    slow_motion: SlowMotion,
+    combo_timer: ComboTimer,
+    /// Pieces falling alongside the active block in frenzy
+    live_pieces: Vec<LivePiece>,
+    /// Slots a T piece could spin into, highlighted by the T-slot hints assist
+    t_slot_hints: PixelContainer,
     score: i64,
-    t: usize,
+    lines_cleared: i64,
+    pieces_placed: usize,
+    /// Progress of the active block towards falling another cell
+    gravity_progress: f32,
+    /// Progress towards the next simulation tick, advanced by the game speed every frame
+    tick_progress: f32,
+    /// Set if soft drop was pressed between frames, to be applied on the next tick
+    soft_drop_pending: bool,
+    /// Whether soft drop has been toggled on, in sticky keys mode
+    soft_drop_toggled: bool,
+    /// The game time, which stops while paused
+    clock: GameClock,
+    /// The average pace of recent games in the mode, if the pace overlay is on
+    pace: Option<Pace>,
+    /// Drops chattering and repeated key presses
+    input_filter: InputFilter,
+    /// Picks the cells that crack or turn into bombs with contamination. Seeded along with the
+    /// pieces in seeded games, but kept apart from them so contamination doesn't change the pieces
+    /// dealt
+    contamination_rng: StdRng,
+    /// The number of frames run so far, which inputs are logged against
+    frame_count: u64,
+    /// Logs every input applied, if the game is being recorded
+    input_recorder: Option<InputRecorder>,
+    /// Inputs from a recorded game to apply instead of the player's, until they run out
+    replay: Option<Replay>,
+    /// Whether the rotation centre and last rotation's kicks are shown, in practice mode
+    rotation_overlay: bool,
+    profiler: Option<Profiler>,
+    /// The hash of the first pieces in seeded games, so racers can check they have the same ones
+    sequence_hash: Option<String>,
     // Constants
+    mode: GameMode,
+    rules: Ruleset,
+    layout: Layout,
+    assists: AssistSettings,
+    controls: Controls,
     controls_help_text: String,
+    quirks: Quirks,
+    sync: SyncSettings,
+    /// Whether borders are drawn between the pieces in the stack
+    connected_pieces: bool,
 }
 
 impl Game {
     pub fn new(
-        block_place_cooldown: u32,
-        piece_preview_count: usize,
-        controls_help_text: &str,
+        rules: Ruleset,
+        mode: GameMode,
+        layout: Layout,
+        controls: Controls,
+        assists: AssistSettings,
+        preferences: Preferences,
     ) -> Self {
-        Self {
-            view: View::new(50, 21, ColChar::EMPTY),
-            alert_display: AlertDisplay::new(Vec2D::new(12, 7)),
-            block_manager: BlockManager::new(block_place_cooldown, piece_preview_count),
-            collision_manager: CollisionManager::new(),
+        let (view_width, view_height) = layout.view_size();
+
+        let mut collision_manager = CollisionManager::new();
+        if let GameMode::Dig(settings) = mode {
+            collision_manager.add_garbage_rows(&garbage::generate_hole_columns(
+                settings.rows,
+                settings,
+                &[],
+                &mut rand::rng(),
+            ));
+        }
+
+        let t_slot_hints = if assists.t_slot_hints {
+            find_t_slots(&collision_manager.stationary_blocks)
+        } else {
+            PixelContainer::new()
+        };
+
+        let mut block_manager = BlockManager::new(
+            rules.gravity_curve.step_for(1).lock_delay,
+            rules.piece_preview_count,
+            rules.hold_slots,
+            rules.randomizer,
+            rules.rotation_system,
+            preferences.piece_colours,
+            mode.seed(),
+        );
+        let sequence_hash = mode.seed().map(|_| block_manager.sequence_hash());
+
+        let mut game = Self {
+            view: View::new(view_width, view_height, ColChar::EMPTY),
+            render_thread: RenderThread::spawn(stdout()),
+            alert_display: AlertDisplay::new(layout.alert_position())
+                .with_settings(preferences.alerts),
+            block_manager,
+            collision_manager,
+            bell: Bell::new(preferences.bell),
+            slow_motion: SlowMotion::default(),
+            combo_timer: ComboTimer::default(),
+            live_pieces: Vec::new(),
+            t_slot_hints,
             score: 0,
-            t: 0,
+            lines_cleared: 0,
+            pieces_placed: 0,
+            gravity_progress: 0.0,
+            tick_progress: 0.0,
+            soft_drop_pending: false,
+            soft_drop_toggled: false,
+            clock: GameClock::stopped(),
+            pace: preferences
+                .pace_overlay
+                .then(|| {
+                    let modifiers = pace_history::modifiers(&rules, mode.speed());
+                    Pace::average(&pace_history::load(), mode.name(), &modifiers)
+                })
+                .flatten(),
+            input_filter: InputFilter::default(),
+            contamination_rng: mode.seed().map_or_else(
+                || StdRng::from_rng(&mut rand::rng()),
+                |seed| StdRng::seed_from_u64(!seed),
+            ),
+            frame_count: 0,
+            input_recorder: None,
+            replay: None,
+            rotation_overlay: false,
+            profiler: None,
+            sequence_hash,
             // Constants
-            controls_help_text: controls_help_text.to_string(),
+            mode,
+            rules,
+            layout,
+            assists,
+            controls_help_text: controls.help_text(),
+            controls,
+            quirks: preferences.quirks,
+            sync: preferences.sync,
+            // The borders are drawn with block characters that ASCII terminals are missing
+            connected_pieces: preferences.connected_pieces && !preferences.quirks.ascii_blocks,
+        };
+
+        // Deal a second piece to fall alongside the first
+        if game.rules.frenzy {
+            game.live_pieces.push(LivePiece {
+                block: game.block_manager.block.clone(),
+                placing_cooldown: game.block_manager.placing_cooldown,
+                gravity_progress: 0.0,
+            });
+            game.block_manager.generate_new_block();
+            game.block_manager
+                .move_clear_of(&game.live_pieces_display());
         }
+
+        game
     }
-}
 
-impl MainLoopRoot for Game {
-    fn get_fps(&self) -> f32 {
-        60.0
+    /// Every live piece other than the active block, to draw and collide with
+    fn live_pieces_display(&self) -> PixelContainer {
+        let mut container = PixelContainer::new();
+        for piece in &self.live_pieces {
+            container.draw(&piece.block);
+        }
+
+        container
     }
 
-    fn frame(&mut self) {
-        self.t += 1;
-        let mut block_speed = 12;
+    /// Every live piece other than the one at the index, including the active block
+    fn other_live_pieces(&self, index: usize) -> PixelContainer {
+        let mut container = PixelContainer::new();
+        container.draw(&self.block_manager.block);
+        for (i, piece) in self.live_pieces.iter().enumerate() {
+            if i != index {
+                container.draw(&piece.block);
+            }
+        }
 
-        // Generate a collision with the current walls and placed blocks
-        let collision = self.collision_manager.get();
+        container
+    }
 
-        // Handle Inputs
-        while let Some(Event::Key(KeyEvent {
-            code,
-            kind: KeyEventKind::Press,
-            ..
-        })) = input::read_and_handle_kb_interrupt(false)
-        {
-            match code {
-                // Pause
-                KeyCode::Esc => {
+    /// Swap the active block with one of the other live pieces, along with its progress towards
+    /// falling and locking
+    fn swap_live_piece(&mut self, index: usize) {
+        let piece = &mut self.live_pieces[index];
+        std::mem::swap(&mut piece.block, &mut self.block_manager.block);
+        std::mem::swap(
+            &mut piece.placing_cooldown,
+            &mut self.block_manager.placing_cooldown,
+        );
+        std::mem::swap(&mut piece.gravity_progress, &mut self.gravity_progress);
+    }
+
+    /// Record how long each phase of every frame takes
+    pub fn with_profiler(mut self, profiler: Option<Profiler>) -> Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// Record the game's inputs, or play back the inputs of a recorded game
+    pub fn with_input_log(
+        mut self,
+        input_recorder: Option<InputRecorder>,
+        replay: Option<Replay>,
+    ) -> Self {
+        self.input_recorder = input_recorder;
+        self.replay = replay;
+        self
+    }
+
+    /// The hash of the first pieces to be dealt, if the game is seeded
+    pub fn sequence_hash(&self) -> Option<&str> {
+        self.sequence_hash.as_deref()
+    }
+
+    /// Record a phase of the frame with the profiler, if there is one
+    fn profile(&mut self, phase: &str, start: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(phase, start);
+        }
+    }
+
+    /// Draw the board with everything on it at its normal size, for the standard and compact
+    /// layouts
+    fn draw_board(&mut self) {
+        // Blit the walls and stationary blocks
+        draw_cells(&mut self.view, self.quirks, &self.collision_manager);
+        if self.connected_pieces {
+            self.view.draw(&self.collision_manager.piece_borders());
+        }
+        if self.bell.is_flashing() {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &generate_borders(ColChar::BACKGROUND),
+            );
+        }
+
+        draw_cells(&mut self.view, self.quirks, &self.t_slot_hints);
+        if self.rules.ghost_piece {
+            draw_cells(&mut self.view, self.quirks, &self.block_manager.ghost_block);
+        }
+        draw_cells(&mut self.view, self.quirks, &self.block_manager.block);
+        let live_pieces = self.live_pieces_display();
+        draw_cells(&mut self.view, self.quirks, &live_pieces);
+        if self.mode == GameMode::Combo {
+            draw_cells(&mut self.view, self.quirks, &self.combo_timer);
+        }
+        if self.rotation_overlay {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &rotation_overlay(
+                    &self.block_manager.block,
+                    self.block_manager.last_rotation.as_ref(),
+                ),
+            );
+        }
+    }
+
+    fn draw_standard_layout(&mut self) {
+        self.draw_board();
+
+        // Next piece display
+        self.view
+            .draw(&Text::new(Vec2D::new(29, 9), "Next:", Modifier::None));
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &self.block_manager.next_piece_display(),
+        );
+
+        // Held piece display
+        if let Some(held_pieces) = self.block_manager.held_pieces_display() {
+            self.view
+                .draw(&Text::new(Vec2D::new(29, 1), "Hold", Modifier::None));
+            draw_cells(&mut self.view, self.quirks, &held_pieces);
+        } else {
+            self.view.draw(&Sprite::new(
+                Vec2D::new(26, 0),
+                &self.controls_help_text,
+                Modifier::None,
+            ));
+        }
+
+        if self.layout == Layout::LargePrint {
+            self.draw_large_print_readouts();
+        } else {
+            // Score display
+            self.view.draw(&Text::new(
+                Vec2D::new(26, 7),
+                &format!("Score: {} | Level {}", self.score, self.level()),
+                Modifier::None,
+            ));
+
+            self.view.draw(&Text::new(
+                Vec2D::new(26, 20),
+                &format!("Time: {}", format_time(self.clock.game_time())),
+                Modifier::None,
+            ));
+        }
+
+        match self.mode {
+            GameMode::Marathon => (),
+            GameMode::Practice { .. } if self.rotation_overlay => self.view.draw(&Text::new(
+                Vec2D::new(26, 8),
+                &describe_kick(self.block_manager.last_rotation.as_ref()),
+                Modifier::None,
+            )),
+            GameMode::Practice { speed, .. } => self.view.draw(&Text::new(
+                Vec2D::new(26, 8),
+                &format!("Practice ({speed}x speed)"),
+                Modifier::None,
+            )),
+            GameMode::Combo => self.view.draw(&Text::new(
+                Vec2D::new(26, 8),
+                &format!(
+                    "Combo: {} | Best {}",
+                    self.combo_timer.chain, self.combo_timer.longest_chain
+                ),
+                Modifier::None,
+            )),
+            GameMode::Dig(_) => self.view.draw(&Text::new(
+                Vec2D::new(26, 8),
+                &format!(
+                    "Garbage left: {} ({:.2}/pc)",
+                    self.collision_manager.garbage_rows_left(),
+                    self.dig_record().map_or(0.0, |record| record.efficiency())
+                ),
+                Modifier::None,
+            )),
+        }
+
+        if let Some(pace) = self.pace {
+            self.draw_pace_overlay(pace);
+        }
+    }
+
+    /// Draw the board with a narrow sidebar holding the oldest held piece, the next piece and
+    /// abbreviated stats
+    fn draw_compact_layout(&mut self) {
+        self.draw_board();
+
+        self.view
+            .draw(&Text::new(Vec2D::new(26, 1), "Hold:", Modifier::None));
+        if let Some(held_pieces) = self.block_manager.held_pieces_display_limited(1) {
+            draw_cells(&mut self.view, self.quirks, &held_pieces);
+        }
+
+        let stats = [format!("Sc {}", self.score), format!("Lv {}", self.level())];
+        for (y, line) in (7..).zip(stats) {
+            self.view
+                .draw(&Text::new(Vec2D::new(26, y), &line, Modifier::None));
+        }
+
+        self.view
+            .draw(&Text::new(Vec2D::new(26, 9), "Next:", Modifier::None));
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &self.block_manager.next_piece_display_limited(1),
+        );
+
+        let mode_stats = match self.mode {
+            GameMode::Marathon => None,
+            GameMode::Practice { speed, .. } => Some(format!("{speed}x speed")),
+            GameMode::Combo => Some(format!(
+                "Combo {}/{}",
+                self.combo_timer.chain, self.combo_timer.longest_chain
+            )),
+            GameMode::Dig(_) => Some(format!(
+                "Dig {} left",
+                self.collision_manager.garbage_rows_left()
+            )),
+        };
+        if let Some(mode_stats) = mode_stats {
+            self.view
+                .draw(&Text::new(Vec2D::new(26, 15), &mode_stats, Modifier::None));
+        }
+
+        self.view.draw(&Text::new(
+            Vec2D::new(26, 20),
+            &format_time(self.clock.game_time()),
+            Modifier::None,
+        ));
+    }
+
+    /// Draw the game's pieces per second and lines cleared beside the next pieces, with how far
+    /// ahead or behind the average of recent games they are
+    fn draw_pace_overlay(&mut self, average: Pace) {
+        let seconds = self.clock.game_time().as_secs_f64();
+        let pieces_per_second = if seconds > 0.0 {
+            self.pieces_placed as f64 / seconds
+        } else {
+            0.0
+        };
+        let expected_lines = average.lines_per_second * seconds;
+
+        let lines = [
+            String::from("vs average:"),
+            format!(
+                "PPS {pieces_per_second:.2} {:+.2}",
+                pieces_per_second - average.pieces_per_second
+            ),
+            format!(
+                "Lines {} {:+.0}",
+                self.lines_cleared,
+                self.lines_cleared as f64 - expected_lines
+            ),
+        ];
+        for (y, line) in (11..).zip(lines) {
+            self.view
+                .draw(&Text::new(Vec2D::new(36, y), &line, Modifier::None));
+        }
+    }
+
+    /// Draw the score, level and timer in large digits in the extra sidebar of the large print
+    /// layout
+    fn draw_large_print_readouts(&mut self) {
+        let readouts = [
+            ("SCORE", self.score.to_string()),
+            ("LEVEL", self.level().to_string()),
+            ("TIME", format_time(self.clock.game_time())),
+        ];
+
+        for (i, (label, value)) in readouts.iter().enumerate() {
+            let y = i as i64 * 7;
+            self.view
+                .draw(&Text::new(Vec2D::new(56, y), label, Modifier::None));
+            self.view.draw(&Quirked::new(
+                &Sprite::new(Vec2D::new(56, y + 1), &big_text(value), Modifier::None),
+                self.quirks,
+            ));
+        }
+    }
+
+    /// Draw an enlarged board between the previews and big score and timer readouts, with the
+    /// player's name above it
+    fn draw_broadcast_layout(&mut self) {
+        const BOARD_OFFSET: Vec2D = BROADCAST_BOARD_OFFSET;
+        const PREVIEW_OFFSET: Vec2D = Vec2D::new(-9, 4);
+
+        if let Layout::Broadcast { player_name } = &self.layout {
+            self.view.draw(
+                &Text::new(Vec2D::new(54, 0), player_name, Modifier::None)
+                    .with_align(TextAlign::Centered),
+            );
+        }
+
+        // Blit the walls, stationary blocks and active blocks at double size
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &Transformed::new(&self.collision_manager, 2, BOARD_OFFSET),
+        );
+        if self.bell.is_flashing() {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &Transformed::new(&generate_borders(ColChar::BACKGROUND), 2, BOARD_OFFSET),
+            );
+        }
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &Transformed::new(&self.t_slot_hints, 2, BOARD_OFFSET),
+        );
+        if self.rules.ghost_piece {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &Transformed::new(&self.block_manager.ghost_block, 2, BOARD_OFFSET),
+            );
+        }
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &Transformed::new(&self.block_manager.block, 2, BOARD_OFFSET),
+        );
+        let live_pieces = self.live_pieces_display();
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &Transformed::new(&live_pieces, 2, BOARD_OFFSET),
+        );
+        if self.mode == GameMode::Combo {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &Transformed::new(&self.combo_timer, 2, BOARD_OFFSET),
+            );
+        }
+
+        // Held and next pieces to the left of the board
+        self.view.draw(
+            &Text::new(Vec2D::new(14, 5), "HOLD", Modifier::None).with_align(TextAlign::Centered),
+        );
+        if let Some(held_pieces) = self.block_manager.held_pieces_display() {
+            draw_cells(
+                &mut self.view,
+                self.quirks,
+                &Transformed::new(&held_pieces, 1, PREVIEW_OFFSET),
+            );
+        }
+        self.view.draw(
+            &Text::new(Vec2D::new(14, 13), "NEXT", Modifier::None).with_align(TextAlign::Centered),
+        );
+        draw_cells(
+            &mut self.view,
+            self.quirks,
+            &Transformed::new(&self.block_manager.next_piece_display(), 1, PREVIEW_OFFSET),
+        );
+
+        // Score and timer to the right of the board
+        self.view
+            .draw(&Text::new(Vec2D::new(82, 5), "SCORE", Modifier::None));
+        self.view.draw(&Quirked::new(
+            &Sprite::new(
+                Vec2D::new(82, 7),
+                &big_text(&self.score.to_string()),
+                Modifier::None,
+            ),
+            self.quirks,
+        ));
+        self.view
+            .draw(&Text::new(Vec2D::new(82, 14), "TIME", Modifier::None));
+        self.view.draw(&Quirked::new(
+            &Sprite::new(
+                Vec2D::new(82, 16),
+                &big_text(&format_time(self.clock.game_time())),
+                Modifier::None,
+            ),
+            self.quirks,
+        ));
+        self.view
+            .draw(&Text::new(Vec2D::new(82, 23), "LEVEL", Modifier::None));
+        self.view.draw(&Quirked::new(
+            &Sprite::new(
+                Vec2D::new(82, 25),
+                &big_text(&self.level().to_string()),
+                Modifier::None,
+            ),
+            self.quirks,
+        ));
+    }
+
+    /// Draw the current state of the game to the screen
+    fn draw_frame(&mut self) {
+        self.view.clear();
+
+        match self.layout {
+            Layout::Standard | Layout::LargePrint => self.draw_standard_layout(),
+            Layout::Compact => self.draw_compact_layout(),
+            Layout::Broadcast { .. } => self.draw_broadcast_layout(),
+        }
+
+        // Alerts display
+        self.view.draw(&self.alert_display);
+
+        self.render_thread
+            .submit(&self.view, self.quirks.clear_each_frame);
+    }
+
+    /// Leave the game without showing the results
+    fn quit(&mut self) -> ! {
+        self.set_mouse_capture(false);
+        // Nothing is dropped once the game exits, so close the trace first
+        self.profiler = None;
+        terminal::exit();
+    }
+
+    /// End the game and show the results, either after topping out or completing the mode's goal
+    fn game_over(&mut self) -> ! {
+        self.clock.stop();
+        self.set_mouse_capture(false);
+        // The results screen exits without returning, so close the trace first
+        self.profiler = None;
+        self.render_thread.flush();
+        self.view.clear();
+        self.view.display_render().expect("Failed to clear screen");
+
+        // Compare the game against earlier ones on boards with as much garbage, before saving it
+        let dig_record = self.dig_record().filter(|record| record.pieces_placed > 0);
+        let mut dig_history = Vec::new();
+        let mut history_saved = false;
+        if let Some(record) = dig_record {
+            dig_history = dig_history::load();
+            dig_history.retain(|previous| {
+                previous.rows == record.rows && previous.relaxed == record.relaxed
+            });
+            // Losing the history isn't worth interrupting the results screen for
+            history_saved |= dig_history::append(record).is_ok();
+        }
+        if let Some(record) = self.pace_record() {
+            history_saved |= pace_history::append(&record).is_ok();
+        }
+        let sync_warnings = if history_saved {
+            self.sync.push()
+        } else {
+            Vec::new()
+        };
+
+        self.results(dig_record, dig_history, sync_warnings).show()
+    }
+
+    /// Summarise the game for the results screen
+    fn results(
+        &self,
+        dig_record: Option<DigRecord>,
+        dig_history: Vec<DigRecord>,
+        sync_warnings: Vec<String>,
+    ) -> Results {
+        let mut board = PixelContainer::new();
+        board.draw(&self.collision_manager);
+        board.draw(&self.block_manager.block);
+
+        Results {
+            score: self.score,
+            game_time: self.clock.game_time(),
+            real_time: self.clock.real_time(),
+            assists: self.assists.enabled_names(),
+            relaxed: self.rules.relaxed,
+            slow_motion_triggers: self.slow_motion.times_triggered,
+            longest_chain: (self.mode == GameMode::Combo).then_some(self.combo_timer.longest_chain),
+            garbage_left: matches!(self.mode, GameMode::Dig(_))
+                .then(|| self.collision_manager.garbage_rows_left()),
+            dig_record,
+            dig_history,
+            sequence_hash: self.sequence_hash.clone(),
+            sync_warnings,
+            board,
+            state: self.snapshot(),
+        }
+    }
+
+    /// The state of the game, in a form that can be saved
+    fn snapshot(&self) -> GameSnapshot {
+        let block = &self.block_manager.block;
+
+        GameSnapshot {
+            version: SNAPSHOT_VERSION,
+            score: self.score,
+            lines_cleared: self.lines_cleared,
+            pieces_placed: self.pieces_placed,
+            rules: self.rules.clone(),
+            piece: PieceSnapshot {
+                shape: block.shape,
+                x: block.pos.x,
+                y: block.pos.y,
+                rotation: block.rotation,
+            },
+            queue: self.block_manager.queue(),
+            held: self.block_manager.held_pieces.iter().copied().collect(),
+            board: CellSnapshot::from_board(&self.collision_manager),
+        }
+    }
+
+    /// Hold the new piece if the one that would come out of hold fits the board better
+    fn auto_hold(&mut self) {
+        let Some(candidate) = self.block_manager.hold_candidate() else {
+            return;
+        };
+
+        let stationary_blocks = &self.collision_manager.stationary_blocks;
+        let rotation_system = self.rules.rotation_system;
+        let score =
+            |shape| best_placement_score(stationary_blocks, rotation_system.rotation_states(shape));
+
+        if score(candidate) > score(self.block_manager.block.shape) {
+            self.block_manager.hold();
+        }
+    }
+
+    /// How well the garbage has been dug through so far, in dig mode
+    fn dig_record(&self) -> Option<DigRecord> {
+        let GameMode::Dig(settings) = self.mode else {
+            return None;
+        };
+
+        Some(DigRecord::new(
+            settings.rows,
+            settings.rows - self.collision_manager.garbage_rows_left(),
+            self.pieces_placed,
+            self.rules.relaxed,
+        ))
+    }
+
+    /// Let blocks left floating by a clear fall, scoring any lines they clear as a chain that's
+    /// worth more the longer it goes on
+    ///
+    /// Returns the number of lines cleared by the chain
+    fn cascade(&mut self, level: u32) -> i64 {
+        let mut chain_lines = 0;
+        let mut chain = 1;
+        while self.collision_manager.settle() {
+            let cleared_lines = self.collision_manager.clear_filled_lines();
+            if cleared_lines == 0 {
+                break;
+            }
+
+            chain_lines += cleared_lines;
+            chain += 1;
+            // A settled group can fill more than four lines at once, which scores as a tetris
+            let lines = cleared_lines.min(4);
+            let alert = match self.rules.scoring {
+                Scoring::Modern => generate_alert_for_filled_lines(lines),
+                Scoring::Classic => generate_classic_alert_for_filled_lines(lines, level),
+            };
+            if let Some((score, _)) = alert {
+                self.score += score * chain;
+            }
+            self.alert_display.push(Message::Cascade { chain });
+        }
+
+        chain_lines
+    }
+
+    /// The game's pace to save to the history, if it was played in a ranked mode
+    fn pace_record(&self) -> Option<PaceRecord> {
+        if matches!(self.mode, GameMode::Practice { .. }) || self.pieces_placed == 0 {
+            return None;
+        }
+
+        Some(PaceRecord::new(
+            self.mode.name(),
+            pace_history::modifiers(&self.rules, self.mode.speed()),
+            self.clock.game_time(),
+            self.pieces_placed,
+            self.lines_cleared as usize,
+        ))
+    }
+
+    /// Handle all inputs that arrived since the last frame
+    ///
+    /// Returns true if the player is soft dropping
+    #[allow(clippy::too_many_lines)]
+    fn handle_input(&mut self) -> bool {
+        let mut soft_drop = false;
+
+        loop {
+            let (action, column) = if let Some(event) = self.next_event() {
+                let Some((action, column)) = self.read_event(&event) else {
+                    continue;
+                };
+                // While a replay plays out, the player can only pause or quit
+                if self.replay.is_some() && action != Action::Pause {
+                    continue;
+                }
+                let kind = match event {
+                    Event::Key(key) => key.kind,
+                    _ => KeyEventKind::Press,
+                };
+                if self.input_filter.ignore(action, kind, self.controls.input) {
+                    continue;
+                }
+                (action, column)
+            } else if let Some(input) = self
+                .replay
+                .as_mut()
+                .and_then(|replay| replay.next(self.frame_count))
+            {
+                input
+            } else {
+                break;
+            };
+
+            // Generate a collision with the current walls, placed blocks and other live pieces
+            let live_pieces = self.live_pieces_display();
+            let mut collision = self.collision_manager.get();
+            collision.push(&live_pieces);
+
+            if let Some(input_recorder) = &mut self.input_recorder {
+                if action != Action::Pause {
+                    input_recorder.record(self.frame_count, action, column);
+                }
+            }
+            if let Some(column) = column {
+                self.block_manager.shift_to_column(&collision, column);
+                self.block_manager.generate_ghost_block(&collision);
+            }
+
+            match action {
+                Action::Pause => {
+                    self.set_mouse_capture(false);
+                    self.render_thread.flush();
                     self.view.clear();
                     self.view.display_render().expect("Failed to clear screen");
-                    pause();
+                    self.clock.stop();
+                    if pause(&pause_details(self.mode, &mut self.block_manager)) {
+                        self.quit();
+                    }
+                    self.clock.resume();
+                    self.set_mouse_capture(true);
                 }
 
-                // Shift left
-                KeyCode::Left => {
+                Action::ShiftLeft => {
                     self.block_manager
                         .try_move_block(&collision, Vec2D::new(-1, 0));
                 }
 
-                // Shift right
-                KeyCode::Right => {
+                Action::ShiftRight => {
                     self.block_manager
                         .try_move_block(&collision, Vec2D::new(1, 0));
                 }
 
-                // Rotate anti-clockwise
-                KeyCode::Char('z') => {
+                Action::RotateAntiClockwise => {
                     self.block_manager.try_rotate_block(&collision, false);
                 }
 
-                // Rotate clockwise
-                KeyCode::Up | KeyCode::Char('x') => {
+                Action::RotateClockwise => {
                     self.block_manager.try_rotate_block(&collision, true);
                 }
 
-                // Soft drop
-                KeyCode::Down => block_speed = 2,
+                Action::SoftDrop if self.controls.input.sticky_keys => {
+                    self.soft_drop_toggled = !self.soft_drop_toggled;
+                }
+
+                Action::SoftDrop => soft_drop = true,
+
+                Action::HardDrop | Action::SonicDrop if !self.rules.hard_drop => (),
 
-                // Hard drop
-                KeyCode::Char(' ') => {
+                Action::HardDrop => {
                     self.score +=
                         self.block_manager.ghost_block.pos.y - self.block_manager.block.pos.y;
                     self.block_manager.block = self.block_manager.ghost_block.clone();
-                    self.t = block_speed - 1;
                     self.block_manager.placing_cooldown = 1;
                 }
 
-                KeyCode::Char('c') => self.block_manager.hold(),
+                Action::SonicDrop => {
+                    while self
+                        .block_manager
+                        .try_move_block(&collision, Vec2D::new(0, 1))
+                    {
+                        self.score += 1;
+                    }
+                }
+
+                Action::Hold => {
+                    self.block_manager.hold();
+                    if !self.live_pieces.is_empty() {
+                        self.block_manager.move_clear_of(&live_pieces);
+                    }
+                }
 
-                _ => (),
+                Action::SwapPiece => {
+                    if !self.live_pieces.is_empty() {
+                        self.swap_live_piece(0);
+                        self.live_pieces.rotate_left(1);
+                        self.block_manager.last_rotation = None;
+                        self.soft_drop_toggled = false;
+                    }
+                }
+
+                Action::ToggleRotationOverlay => {
+                    if matches!(self.mode, GameMode::Practice { .. }) {
+                        self.rotation_overlay = !self.rotation_overlay;
+                    }
+                }
             }
         }
 
-        // Place the ghost block directly beneath the active block
+        // Place the ghost block directly beneath the active block, which may have been swapped
+        let live_pieces = self.live_pieces_display();
+        let mut collision = self.collision_manager.get();
+        collision.push(&live_pieces);
         self.block_manager.generate_ghost_block(&collision);
 
+        soft_drop || self.soft_drop_toggled
+    }
+
+    /// Read the next waiting input event, if there is one. Ctrl+C quits the game
+    fn next_event(&mut self) -> Option<Event> {
+        if !poll(Duration::ZERO).expect("Failed to poll input") {
+            return None;
+        }
+        let event = read().expect("Failed to read input");
+        if let Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        }) = event
+        {
+            self.quit();
+        }
+
+        Some(event)
+    }
+
+    /// The action an input event triggers, and the column to drop the piece into if it's a quick
+    /// place
+    fn read_event(&self, event: &Event) -> Option<(Action, Option<i64>)> {
+        if let Some(column) = self.quick_place_column(event) {
+            return Some((Action::HardDrop, Some(column)));
+        }
+
+        match *event {
+            Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) => self
+                .controls
+                .get_action(code, modifiers)
+                .map(|action| (action, None)),
+            _ => None,
+        }
+    }
+
+    /// The column to drop the active block into, if quick place is on and the event picks one:
+    /// a number key that isn't bound to anything else, or a click on the board
+    fn quick_place_column(&self, event: &Event) -> Option<i64> {
+        if !self.controls.input.quick_place {
+            return None;
+        }
+
+        match *event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(key),
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) if self
+                .controls
+                .get_action(KeyCode::Char(key), modifiers)
+                .is_none() =>
+            {
+                match key.to_digit(10)? {
+                    0 => Some(10),
+                    digit => Some(i64::from(digit)),
+                }
+            }
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                ..
+            }) => self.layout.board_column(column),
+            _ => None,
+        }
+    }
+
+    /// Turn mouse reporting on or off, if quick place needs it for clicking on columns
+    fn set_mouse_capture(&self, enabled: bool) {
+        if !self.controls.input.quick_place {
+            return;
+        }
+
+        let _ = if enabled {
+            execute!(stdout(), EnableMouseCapture)
+        } else {
+            execute!(stdout(), DisableMouseCapture)
+        };
+    }
+
+    /// Advance the simulation by one tick, applying gravity and locking the active block
+    fn tick(&mut self, soft_drop: bool) {
+        let step = self.rules.gravity_curve.step_for(self.level());
+        self.block_manager.set_block_place_cooldown(step.lock_delay);
+
+        let mut gravity = step.cells_per_frame;
+        if self.assists.slow_motion_on_danger {
+            let was_slowed = self.slow_motion.is_active();
+            if self.slow_motion.tick(self.collision_manager.is_in_danger()) {
+                gravity /= 2.0;
+            }
+            if self.slow_motion.is_active() && !was_slowed {
+                self.alert_display.push(Message::SlowMotion);
+            }
+        }
+
+        if self.mode == GameMode::Combo && self.combo_timer.tick() {
+            self.alert_display.push(Message::ComboBroken);
+        }
+
+        // Soft drop overrides slow motion
+        let active_gravity = if soft_drop {
+            step.cells_per_frame.max(SOFT_DROP_GRAVITY)
+        } else {
+            gravity
+        };
+        self.fall(active_gravity, soft_drop);
+
+        for i in 0..self.live_pieces.len() {
+            self.fall_live_piece(i, gravity);
+        }
+    }
+
+    /// Move one of the other live pieces down as far as gravity has built up, or lock it once it
+    /// has rested on the ground for long enough
+    fn fall_live_piece(&mut self, index: usize, gravity: f32) {
+        let other_pieces = self.other_live_pieces(index);
+        let mut collision = self.collision_manager.get();
+        collision.push(&other_pieces);
+        let place_cooldown = self.block_manager.place_cooldown();
+        let piece = &mut self.live_pieces[index];
+
+        if collision.will_overlap_element(&piece.block, Vec2D::new(0, 1)) {
+            piece.gravity_progress = 0.0;
+            piece.placing_cooldown -= 1;
+            if piece.placing_cooldown == 0 {
+                self.lock_live_piece(index);
+            }
+        } else {
+            piece.gravity_progress += gravity;
+            let cells = piece.gravity_progress.floor();
+            piece.gravity_progress -= cells;
+
+            for _ in 0..cells as usize {
+                if !tetris_core::try_move_block(&collision, &mut piece.block, Vec2D::new(0, 1)) {
+                    break;
+                }
+                piece.placing_cooldown = place_cooldown;
+            }
+        }
+    }
+
+    /// Lock one of the other live pieces in place and deal another in its place. The player
+    /// didn't drop it, so it skips everything tied to their own drops, like T-spins, hard drop
+    /// protection and holding
+    fn lock_live_piece(&mut self, index: usize) {
+        let block = self.live_pieces[index].block.clone();
+        if block.pos.y < 1 {
+            self.game_over();
+        }
+        self.add_to_stack(&block, false);
+
+        let mut piece = self.block_manager.deal_live_piece();
+        if !piece.move_clear_of(&self.other_live_pieces(index)) {
+            self.game_over();
+        }
+        self.live_pieces[index] = piece;
+    }
+
+    /// Move the active block down as far as gravity has built up, or lock it once it has rested
+    /// on the ground for long enough
+    fn fall(&mut self, gravity: f32, soft_drop: bool) {
+        let live_pieces = self.live_pieces_display();
+        let mut collision = self.collision_manager.get();
+        collision.push(&live_pieces);
+
         // If the active block is on the floor...
         if collision.will_overlap_element(&self.block_manager.block, Vec2D::new(0, 1)) {
             // If the block's way down is blocked...
+            self.gravity_progress = 0.0;
             self.block_manager.placing_cooldown -= 1;
             if self.block_manager.placing_cooldown == 0 {
-                let pre_clear_blocks = self.collision_manager.stationary_blocks.clone();
+                self.lock_block();
+            }
+        } else {
+            // Fall as many whole cells as the gravity has built up
+            self.gravity_progress += gravity;
+            let cells = self.gravity_progress.floor();
+            self.gravity_progress -= cells;
 
-                // If the current block is at the very top of the board...
-                if self.block_manager.reset() {
-                    println!("Game over!\r");
-                    input::exit_raw_mode();
+            for _ in 0..cells as usize {
+                if !self
+                    .block_manager
+                    .try_move_block(&collision, Vec2D::new(0, 1))
+                {
+                    break;
                 }
+                // Increase score for soft drop
+                if soft_drop {
+                    self.score += 1;
+                }
+            }
+        }
+    }
+
+    /// Lock the active block in place, clear any filled lines and deal the next block
+    fn lock_block(&mut self) {
+        self.input_filter.piece_locked();
+
+        // If the current block is at the very top of the board...
+        if self.block_manager.reset() {
+            self.game_over();
+        }
+
+        let block = self.block_manager.block.clone();
+        self.add_to_stack(&block, true);
+
+        self.block_manager.generate_new_block();
+        self.soft_drop_toggled = false;
+        if self.assists.auto_hold {
+            self.auto_hold();
+        }
+        if !self.live_pieces.is_empty()
+            && !self
+                .block_manager
+                .move_clear_of(&self.live_pieces_display())
+        {
+            self.game_over();
+        }
+    }
+
+    /// Add a locked block to the stack, clearing and scoring any lines it fills. Only the
+    /// active block can score a T-spin, since only the player rotates it
+    fn add_to_stack(&mut self, block: &Block, is_active: bool) {
+        let pre_clear_blocks = self.collision_manager.stationary_blocks.clone();
+        let was_in_danger = self.collision_manager.is_in_danger();
+
+        let mut cells = PixelContainer::new();
+        cells.draw(block);
+        if self.rules.contamination {
+            self.collision_manager
+                .contaminate(&mut cells.pixels, &mut self.contamination_rng);
+        }
+        let cleared_lines = self.collision_manager.draw_and_clear_lines(&cells);
+        let level = self.level();
+        self.lines_cleared += cleared_lines;
+        self.pieces_placed += 1;
 
-                let cleared_lines = self
-                    .collision_manager
-                    .draw_and_clear_lines(&self.block_manager.block);
+        let mut bell_events = vec![BellEvent::Lock];
+        if cleared_lines > 0 {
+            bell_events.push(BellEvent::Clear);
+        }
+        if self.collision_manager.is_in_danger() && !was_in_danger {
+            bell_events.push(BellEvent::Warning);
+        }
+        self.bell.ring(&bell_events);
 
-                // Display an appropriate alert
-                self.alert_display.priorised_alerts_with_score(
-                    &[
+        // Display an appropriate alert
+        match self.rules.scoring {
+            Scoring::Modern => self.alert_display.priorised_alerts_with_score(
+                &[
+                    if is_active {
                         self.block_manager
-                            .check_for_t_spin(&pre_clear_blocks, cleared_lines),
-                        generate_alert_for_filled_lines(cleared_lines),
-                    ],
-                    &mut self.score,
-                );
+                            .check_for_t_spin(&pre_clear_blocks, cleared_lines)
+                    } else {
+                        None
+                    },
+                    generate_alert_for_filled_lines(cleared_lines),
+                ],
+                &mut self.score,
+            ),
+            Scoring::Classic => self.alert_display.handle_with_score(
+                &mut self.score,
+                generate_classic_alert_for_filled_lines(cleared_lines, level),
+            ),
+        }
 
-                self.block_manager.generate_new_block();
-            }
-        } else if self.t % block_speed == 0 {
-            // move down and increase score for soft drop
-            self.block_manager
-                .try_move_block(&collision, Vec2D::new(0, 1));
-            if block_speed == 2 {
-                self.score += 1;
+        if self.rules.cascade_gravity && cleared_lines > 0 {
+            self.lines_cleared += self.cascade(level);
+        }
+
+        if self.mode == GameMode::Combo && cleared_lines > 0 {
+            self.combo_timer.clear_lines();
+            if self.combo_timer.chain > 1 {
+                self.alert_display.push(Message::Combo {
+                    chain: self.combo_timer.chain,
+                });
             }
         }
+
+        if matches!(self.mode, GameMode::Dig(_)) && self.collision_manager.garbage_rows_left() == 0
+        {
+            self.game_over();
+        }
+
+        if self.assists.t_slot_hints {
+            self.t_slot_hints = find_t_slots(&self.collision_manager.stationary_blocks);
+        }
+
+        // Cascades can settle the stack into the pieces still falling, so push them back out
+        if cleared_lines > 0 {
+            self.lift_live_pieces(!is_active);
+        }
     }
 
-    fn render_frame(&mut self) {
-        self.view.clear();
+    /// Push every live piece up out of the stack, ending the game if one has no room left. The
+    /// active block is left alone when it's the one that was just locked
+    fn lift_live_pieces(&mut self, include_active: bool) {
+        let collision = self.collision_manager.get();
+        let mut fits = self
+            .live_pieces
+            .iter_mut()
+            .all(|piece| tetris_core::lift_clear_of(&collision, &mut piece.block));
+        if include_active {
+            fits &= tetris_core::lift_clear_of(&collision, &mut self.block_manager.block);
+        }
 
-        // Blit the walls and stationary blocks
-        self.view.draw_double_width(&self.collision_manager);
+        if !fits {
+            self.game_over();
+        }
+    }
 
-        self.view.draw_double_width(&self.block_manager.ghost_block);
-        self.view.draw_double_width(&self.block_manager.block);
+    /// The current level, going up every `LINES_PER_LEVEL` cleared lines
+    const fn level(&self) -> u32 {
+        (self.lines_cleared / LINES_PER_LEVEL) as u32 + 1
+    }
+}
 
-        // Next piece display
-        self.view
-            .draw(&Text::new(Vec2D::new(29, 9), "Next:", Modifier::None));
-        self.view
-            .draw_double_width(&self.block_manager.next_piece_display());
+/// The seed and upcoming pieces to show on the pause screen, if the player asked to see them
+fn pause_details(mode: GameMode, block_manager: &mut BlockManager) -> Vec<String> {
+    let GameMode::Practice {
+        seed,
+        reveal_sequence,
+        ..
+    } = mode
+    else {
+        return Vec::new();
+    };
 
-        // Held piece display
-        if let Some(held_piece) = self.block_manager.held_piece_display() {
-            self.view
-                .draw(&Text::new(Vec2D::new(29, 1), "Hold", Modifier::None));
-            self.view.draw_double_width(&held_piece);
-        } else {
-            self.view.draw(&Sprite::new(
-                Vec2D::new(26, 0),
-                &self.controls_help_text,
-                Modifier::None,
-            ));
+    let mut details = Vec::new();
+    if let Some(seed) = seed {
+        details.push(format!("Seed: {seed}"));
+    }
+    if reveal_sequence {
+        details.push(String::from("Upcoming pieces:"));
+        let upcoming: Vec<char> = block_manager
+            .upcoming_pieces(REVEALED_PIECE_COUNT)
+            .into_iter()
+            .map(BlockType::letter)
+            .collect();
+        // Split into groups of 7 to make the sequence easier to read
+        for line in upcoming.chunks(35) {
+            let groups: Vec<String> = line.chunks(7).map(|group| group.iter().collect()).collect();
+            details.push(groups.join(" "));
         }
+    }
 
-        // Score display
-        self.view.draw(&Text::new(
-            Vec2D::new(26, 7),
-            &format!("Score: {}", self.score),
-            Modifier::None,
-        ));
+    details
+}
 
-        // Alerts display
-        self.view.draw(&self.alert_display);
+/// Format the time as `mm:ss.cc`
+fn format_time(time: Duration) -> String {
+    let centiseconds = time.as_millis() / 10;
+    format!(
+        "{:02}:{:02}.{:02}",
+        centiseconds / 6000,
+        centiseconds / 100 % 60,
+        centiseconds % 100
+    )
+}
+
+impl MainLoopRoot for Game {
+    fn get_fps(&self) -> f32 {
+        60.0
+    }
+
+    fn frame(&mut self) {
+        let input_start = Instant::now();
+        let soft_drop = self.handle_input() | std::mem::take(&mut self.soft_drop_pending);
+        self.profile("input", input_start);
+
+        // Run as many simulation ticks as the game speed calls for
+        let simulation_start = Instant::now();
+        self.tick_progress += self.mode.speed();
+        let ticks = self.tick_progress.floor();
+        self.tick_progress -= ticks;
+        for _ in 0..ticks as usize {
+            self.tick(soft_drop);
+        }
+        self.profile("simulation", simulation_start);
+
+        self.frame_count += 1;
+        // Hand control back to the player once a replay has played out
+        if self.replay.as_ref().is_some_and(Replay::is_finished) {
+            self.replay = None;
+        }
+    }
+
+    fn render_frame(&mut self) {
+        let render_start = Instant::now();
+        self.draw_frame();
+        self.bell.frame();
         self.alert_display.frame();
-        #[cfg(windows)]
-        {
-            use crossterm::{
-                cursor::MoveTo,
-                execute,
-                terminal::{Clear, ClearType},
+        self.profile("render", render_start);
+    }
+
+    /// Sleep until either input arrives or the next frame is due, instead of sleeping through
+    /// whole frames. Input is handled and shown as soon as it arrives, while gravity and timers
+    /// still advance once per frame
+    fn main_loop(&mut self) {
+        let frame_length = Duration::from_secs_f32(1.0 / self.get_fps());
+        let mut next_frame = Instant::now() + frame_length;
+        // Asked before the clock starts, since a terminal that doesn't answer holds it up
+        self.input_filter.repeats_reported = terminal::report_key_repeats();
+        // Start the clock now rather than when the game was created, in case it waited to start
+        self.clock = GameClock::start();
+        self.set_mouse_capture(true);
+
+        loop {
+            while let Some(remaining) = next_frame.checked_duration_since(Instant::now()) {
+                if !poll(remaining).expect("Failed to poll input") {
+                    break;
+                }
+                let input_start = Instant::now();
+                self.soft_drop_pending |= self.handle_input();
+                self.profile("input", input_start);
+
+                let render_start = Instant::now();
+                self.draw_frame();
+                self.profile("render", render_start);
+            }
+
+            let frame_start = Instant::now();
+            self.frame();
+            self.render_frame();
+            if let Some(profiler) = &mut self.profiler {
+                profiler.end_frame(frame_start);
+            }
+
+            next_frame += frame_length;
+            // Don't rush through the frames missed while paused or stalled
+            let now = Instant::now();
+            if next_frame < now {
+                next_frame = now + frame_length;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::assert_golden;
+    use gemini_engine::primitives::Pixel;
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    /// Frames written by the render thread, kept in memory instead of going to the terminal
+    #[derive(Clone, Default)]
+    struct FrameBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for FrameBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0
+                .lock()
+                .expect("Frame buffer poisoned")
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A practice game that always deals the same pieces
+    fn seeded_game(layout: Layout) -> Game {
+        Game::new(
+            Ruleset::modern(),
+            GameMode::Practice {
+                speed: 1.0,
+                seed: Some(1),
+                reveal_sequence: true,
+            },
+            layout,
+            Controls::new(KeyboardLayout::Qwerty),
+            AssistSettings::default(),
+            Preferences::default(),
+        )
+    }
+
+    /// A game with a row missing one cell, and a block floating above the gap
+    fn cascading_game(scoring: Scoring) -> Game {
+        let mut game = seeded_game(Layout::Standard);
+        game.rules.scoring = scoring;
+        for x in (1..11).filter(|x| *x != 5) {
+            game.collision_manager
+                .draw(&Pixel::new(Vec2D::new(x, 19), ColChar::SOLID));
+        }
+        game.collision_manager
+            .draw(&Pixel::new(Vec2D::new(5, 10), ColChar::SOLID));
+        game
+    }
+
+    #[test]
+    fn cascades_settle_and_score_the_chain() {
+        let mut game = cascading_game(Scoring::Modern);
+        assert_eq!(game.cascade(1), 1);
+        assert!(game.collision_manager.stationary_blocks.pixels.is_empty());
+        // A single, doubled for being the second clear in the chain
+        assert_eq!(game.score, 200);
+    }
+
+    #[test]
+    fn cascades_score_like_the_rest_of_the_game_with_classic_scoring() {
+        let mut game = cascading_game(Scoring::Classic);
+        assert_eq!(game.cascade(3), 1);
+        // A classic single at level 3, doubled for being the second clear in the chain
+        assert_eq!(game.score, 40 * 3 * 2);
+    }
+
+    #[test]
+    fn live_pieces_are_pushed_out_of_a_stack_that_settles_into_them() {
+        let mut game = seeded_game(Layout::Standard);
+        let mut block = game.block_manager.block.clone();
+        block.pos = Vec2D::new(5, 10);
+        game.live_pieces.push(LivePiece {
+            block,
+            placing_cooldown: 1,
+            gravity_progress: 0.0,
+        });
+        let mut stack = PixelContainer::new();
+        stack.draw(&game.live_pieces[0].block);
+        game.collision_manager.draw(&stack);
+
+        game.lift_live_pieces(false);
+        let piece = &game.live_pieces[0].block;
+        assert!(piece.pos.y < 10);
+        assert!(!game.collision_manager.get().overlaps_element(piece));
+    }
+
+    fn hard_drop(game: &mut Game) {
+        let collision = game.collision_manager.get();
+        game.block_manager.generate_ghost_block(&collision);
+        game.block_manager.block = game.block_manager.ghost_block.clone();
+        game.block_manager.placing_cooldown = 1;
+        game.tick(false);
+    }
+
+    /// Draw a frame and return the text written for it, without escape codes
+    fn render(game: &mut Game) -> String {
+        let output = FrameBuffer::default();
+        game.render_thread = RenderThread::spawn(output.clone());
+        game.draw_frame();
+        game.render_thread.flush();
+
+        let bytes = output.0.lock().expect("Frame buffer poisoned").clone();
+        strip_escape_codes(&String::from_utf8(bytes).expect("Frame isn't valid UTF-8"))
+    }
+
+    fn strip_escape_codes(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                // Control sequences end with a character between @ and ~
+                '\x1b' => {
+                    if chars.next() == Some('[') {
+                        for c in chars.by_ref() {
+                            if ('@'..='~').contains(&c) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                '\r' => (),
+                _ => stripped.push(c),
+            }
+        }
+        stripped
+    }
+
+    fn assert_layout_golden(name: &str, layout: Layout) {
+        let mut game = seeded_game(layout);
+        for _ in 0..3 {
+            hard_drop(&mut game);
+        }
+        assert_golden("golden", name, &render(&mut game));
+    }
+
+    #[test]
+    fn standard_layout_matches_golden() {
+        assert_layout_golden("standard_layout", Layout::Standard);
+    }
+
+    #[test]
+    fn compact_layout_matches_golden() {
+        assert_layout_golden("compact_layout", Layout::Compact);
+    }
+
+    #[test]
+    fn large_print_layout_matches_golden() {
+        assert_layout_golden("large_print_layout", Layout::LargePrint);
+    }
+
+    #[test]
+    fn broadcast_layout_matches_golden() {
+        assert_layout_golden(
+            "broadcast_layout",
+            Layout::Broadcast {
+                player_name: String::from("Player"),
+            },
+        );
+    }
+
+    #[test]
+    fn pause_screen_matches_golden() {
+        let mut game = seeded_game(Layout::Standard);
+        let details = pause_details(game.mode, &mut game.block_manager);
+        assert_golden("golden", "pause_screen", &(details.join("\n") + "\n"));
+    }
+
+    #[test]
+    fn sequence_hash_depends_on_seed() {
+        let hash = |seed| {
+            let mode = GameMode::Practice {
+                speed: 1.0,
+                seed,
+                reveal_sequence: false,
             };
-            use std::io::stdout;
-            execute!(stdout(), MoveTo(0, 0), Clear(ClearType::FromCursorDown)).unwrap();
+            let game = Game::new(
+                Ruleset::modern(),
+                mode,
+                Layout::Standard,
+                Controls::new(KeyboardLayout::Qwerty),
+                AssistSettings::default(),
+                Preferences::default(),
+            );
+            game.sequence_hash().map(String::from)
+        };
+
+        assert_eq!(hash(Some(1)), hash(Some(1)));
+        assert_ne!(hash(Some(1)), hash(Some(2)));
+        assert_eq!(hash(None), None);
+    }
+
+    #[test]
+    fn results_screen_matches_golden() {
+        let mut game = seeded_game(Layout::Standard);
+        for _ in 0..5 {
+            hard_drop(&mut game);
         }
-        self.view
-            .display_render()
-            .expect("Failed to print render to screen");
+        assert_golden(
+            "golden",
+            "results_screen",
+            &game.results(None, Vec::new(), Vec::new()).to_text(false),
+        );
     }
 }
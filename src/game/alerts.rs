@@ -2,23 +2,98 @@ use gemini_engine::{
     ascii::{Text, TextAlign},
     core::{CanDraw, Modifier, Vec2D},
 };
+use serde::Deserialize;
+
+use super::messages::{Language, Message};
 
 const ALERT_LIFETIME: u16 = 20;
 
-pub fn generate_alert_for_filled_lines(cleared_lines: i64) -> Option<(i64, String)> {
+/// Which alerts are shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertVerbosity {
+    #[default]
+    All,
+    /// Only tetrises and T-spins
+    Important,
+    None,
+}
+
+impl AlertVerbosity {
+    const fn allows(self, message: Message) -> bool {
+        match self {
+            Self::All => true,
+            Self::Important => message.is_important(),
+            Self::None => false,
+        }
+    }
+}
+
+/// The `[alerts]` section of the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    pub show: AlertVerbosity,
+    /// How many frames each alert stays on screen for
+    pub lifetime: u16,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            show: AlertVerbosity::All,
+            lifetime: ALERT_LIFETIME,
+        }
+    }
+}
+
+impl AlertSettings {
+    pub fn validate(self) -> Result<Self, String> {
+        if self.lifetime == 0 {
+            Err(String::from(
+                "alerts must stay on screen for at least 1 frame",
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+pub fn generate_alert_for_filled_lines(cleared_lines: i64) -> Option<(i64, Message)> {
+    let message = Message::LineClear {
+        lines: cleared_lines,
+    };
     match cleared_lines {
-        1 => Some((100, String::from("Single!"))),
-        2 => Some((300, String::from("Double!"))),
-        3 => Some((500, String::from("Triple!"))),
-        4 => Some((800, String::from("Tetris!"))),
+        1 => Some((100, message)),
+        2 => Some((300, message)),
+        3 => Some((500, message)),
+        4 => Some((800, message)),
         0 => None,
         _ => panic!("entered value should be between 0 and 4"),
     }
 }
 
+/// Score line clears like the NES version, multiplying its scores by the level
+pub fn generate_classic_alert_for_filled_lines(
+    cleared_lines: i64,
+    level: u32,
+) -> Option<(i64, Message)> {
+    generate_alert_for_filled_lines(cleared_lines).map(|(_, alert)| {
+        let score = match cleared_lines {
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            _ => 1200,
+        };
+        (score * i64::from(level), alert)
+    })
+}
+
 pub struct AlertDisplay {
     pub pos: Vec2D,
     alerts: Vec<(String, u16)>,
+    settings: AlertSettings,
+    language: Language,
 }
 
 impl AlertDisplay {
@@ -26,29 +101,43 @@ impl AlertDisplay {
         Self {
             pos,
             alerts: vec![],
+            settings: AlertSettings {
+                show: AlertVerbosity::All,
+                lifetime: ALERT_LIFETIME,
+            },
+            language: Language::English,
         }
     }
 
-    pub fn push(&mut self, alert: &str) {
-        self.alerts.push((String::from(alert), ALERT_LIFETIME));
+    pub const fn with_settings(mut self, settings: AlertSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Show the alert, unless the player has chosen to hide alerts like it
+    pub fn push(&mut self, alert: Message) {
+        if self.settings.show.allows(alert) {
+            self.alerts
+                .push((self.language.format(alert), self.settings.lifetime));
+        }
     }
 
-    pub fn handle_with_score(&mut self, score: &mut i64, score_and_alert: Option<(i64, String)>) {
+    pub fn handle_with_score(&mut self, score: &mut i64, score_and_alert: Option<(i64, Message)>) {
         if let Some((add_score, alert)) = score_and_alert {
             *score += add_score;
-            self.push(&alert);
+            self.push(alert);
         }
     }
 
     /// Will pick the first existing alert score pair and run `handle_with_score` on that
     pub fn priorised_alerts_with_score(
         &mut self,
-        alert_score_pairs: &[Option<(i64, String)>],
+        alert_score_pairs: &[Option<(i64, Message)>],
         score: &mut i64,
     ) {
         for score_alert_pair in alert_score_pairs {
             if score_alert_pair.is_some() {
-                self.handle_with_score(score, score_alert_pair.clone());
+                self.handle_with_score(score, *score_alert_pair);
                 break;
             }
         }
@@ -0,0 +1,220 @@
+use super::block_manager::{Block, BlockType};
+use gemini_engine::{
+    containers::PixelContainer,
+    core::{ColChar, Colour, Vec2D},
+    primitives::Pixel,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// How many ticks gravity stays halved for once slow motion kicks in
+const SLOW_MOTION_LENGTH: usize = 600;
+/// How many ticks after slow motion ends before it can kick in again
+const SLOW_MOTION_COOLDOWN: usize = 1800;
+/// How much lines cleared, total column height, holes and bumpiness count towards how good a
+/// placement is, in that order
+const PLACEMENT_WEIGHTS: [f64; 4] = [0.76, -0.51, -0.36, -0.18];
+
+/// The `[assists]` section of the config file. Games played with any assist enabled are marked
+/// as assisted on the results screen
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct AssistSettings {
+    /// Halve gravity for a while when the stack reaches the top four rows
+    pub slow_motion_on_danger: bool,
+    /// Highlight slots a T piece could spin into to clear two or more lines. Only available in
+    /// practice mode
+    pub t_slot_hints: bool,
+    /// Hold automatically whenever the piece that would come out of hold fits the board better
+    /// than the new one
+    pub auto_hold: bool,
+}
+
+impl AssistSettings {
+    /// The names of every enabled assist
+    pub fn enabled_names(self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.slow_motion_on_danger {
+            names.push("slow motion on danger");
+        }
+        if self.t_slot_hints {
+            names.push("T-slot hints");
+        }
+        if self.auto_hold {
+            names.push("auto hold");
+        }
+        names
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SlowMotion {
+    remaining: usize,
+    cooldown: usize,
+    pub times_triggered: usize,
+}
+
+impl SlowMotion {
+    /// Advance by one tick, starting slow motion if the stack has just become dangerous
+    ///
+    /// Returns true if gravity should be halved for this tick
+    pub const fn tick(&mut self, is_in_danger: bool) -> bool {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                self.cooldown = SLOW_MOTION_COOLDOWN;
+            }
+            return true;
+        }
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        } else if is_in_danger {
+            self.remaining = SLOW_MOTION_LENGTH;
+            self.times_triggered += 1;
+        }
+
+        false
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+/// Find every slot a T piece could spin into to clear at least two lines, and fill them in with a
+/// faint T so they can be drawn over the board
+///
+/// A slot needs three of the four corners around the T's centre to be filled, like a real T-spin,
+/// and has to be covered from above, since otherwise the T could just be dropped in
+pub fn find_t_slots(stationary_blocks: &PixelContainer) -> PixelContainer {
+    let filled: HashSet<Vec2D> = stationary_blocks
+        .pixels
+        .iter()
+        .map(|pixel| pixel.pos)
+        .collect();
+    // The walls and floor count as filled, but the space above the board doesn't
+    let is_filled = |pos: Vec2D| !(1..=10).contains(&pos.x) || pos.y >= 20 || filled.contains(&pos);
+
+    let hint_char = ColChar::BACKGROUND.with_colour(Colour::rgb(128, 0, 128));
+    let mut hints = PixelContainer::new();
+
+    for state in Block::new(BlockType::T).rotation_states() {
+        for y in 0..20 {
+            for x in 1..=10 {
+                let centre = Vec2D::new(x, y);
+                let cells: Vec<Vec2D> = state.iter().map(|cell| centre + *cell).collect();
+
+                let fits = cells.iter().all(|cell| cell.y >= 0 && !is_filled(*cell));
+                let resting = cells.iter().any(|cell| {
+                    let below = *cell + Vec2D::new(0, 1);
+                    !cells.contains(&below) && is_filled(below)
+                });
+                let filled_corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+                    .into_iter()
+                    .filter(|&(dx, dy)| is_filled(centre + Vec2D::new(dx, dy)))
+                    .count();
+                let covered = cells.iter().any(|cell| {
+                    (0..cell.y).any(|above| filled.contains(&Vec2D::new(cell.x, above)))
+                });
+
+                if !(fits && resting && filled_corners >= 3 && covered) {
+                    continue;
+                }
+
+                let mut rows: Vec<i64> = cells.iter().map(|cell| cell.y).collect();
+                rows.sort_unstable();
+                rows.dedup();
+                let cleared_lines = rows
+                    .iter()
+                    .filter(|&&row| {
+                        (1..=10).all(|column| {
+                            let pos = Vec2D::new(column, row);
+                            cells.contains(&pos) || filled.contains(&pos)
+                        })
+                    })
+                    .count();
+
+                if cleared_lines >= 2 {
+                    for cell in cells {
+                        hints.push(Pixel::new(cell, hint_char));
+                    }
+                }
+            }
+        }
+    }
+
+    hints
+}
+
+/// Rate the best spot a piece could be dropped straight down into, by the lines it clears and the
+/// height, holes and bumpiness it leaves behind. Higher is better
+pub fn best_placement_score(
+    stationary_blocks: &PixelContainer,
+    rotation_states: &[Vec<Vec2D>],
+) -> f64 {
+    let filled: HashSet<Vec2D> = stationary_blocks
+        .pixels
+        .iter()
+        .map(|pixel| pixel.pos)
+        .collect();
+    let is_filled = |pos: Vec2D| !(1..=10).contains(&pos.x) || pos.y >= 20 || filled.contains(&pos);
+
+    let mut best = f64::NEG_INFINITY;
+    for state in rotation_states {
+        for x in 0..=11 {
+            // Start above the board, and give up on columns the piece can't be dropped down
+            let mut pos = Vec2D::new(x, -4);
+            if state.iter().any(|cell| is_filled(pos + *cell)) {
+                continue;
+            }
+            while state
+                .iter()
+                .all(|cell| !is_filled(pos + *cell + Vec2D::new(0, 1)))
+            {
+                pos.y += 1;
+            }
+
+            let cells: Vec<Vec2D> = state.iter().map(|cell| pos + *cell).collect();
+            // Pieces left sticking out of the top would lose the game
+            if cells.iter().any(|cell| cell.y < 0) {
+                continue;
+            }
+
+            let mut rows = [[false; 10]; 20];
+            for cell in filled.iter().chain(&cells) {
+                if (0..20).contains(&cell.y) && (1..=10).contains(&cell.x) {
+                    rows[cell.y as usize][cell.x as usize - 1] = true;
+                }
+            }
+            let remaining: Vec<[bool; 10]> = rows
+                .into_iter()
+                .filter(|row| !row.iter().all(|&cell| cell))
+                .collect();
+            let cleared_lines = 20 - remaining.len();
+
+            let mut heights = [0; 10];
+            let mut holes = 0;
+            for (column, height) in heights.iter_mut().enumerate() {
+                if let Some(top) = remaining.iter().position(|row| row[column]) {
+                    *height = remaining.len() - top;
+                    holes += remaining[top..].iter().filter(|row| !row[column]).count();
+                }
+            }
+            let total_height: usize = heights.iter().sum();
+            let bumpiness: usize = heights
+                .windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]))
+                .sum();
+
+            let score: f64 = PLACEMENT_WEIGHTS
+                .iter()
+                .zip([cleared_lines, total_height, holes, bumpiness])
+                .map(|(weight, value)| weight * value as f64)
+                .sum();
+            best = best.max(score);
+        }
+    }
+
+    best
+}
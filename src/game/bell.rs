@@ -0,0 +1,96 @@
+use serde::Deserialize;
+use std::io::{stdout, Write};
+
+/// How many frames the walls stay highlighted for after a visual bell
+const FLASH_LENGTH: u8 = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellStyle {
+    #[default]
+    Off,
+    /// Ring the terminal's own bell
+    Terminal,
+    /// Flash the walls of the board
+    Visual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BellEvent {
+    /// A piece was locked in place
+    Lock,
+    /// One or more lines were cleared
+    Clear,
+    /// The stack has just reached the top of the board
+    Warning,
+}
+
+/// The `[bell]` section of the config file
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct BellSettings {
+    pub style: BellStyle,
+    pub on_lock: bool,
+    pub on_clear: bool,
+    pub on_warning: bool,
+}
+
+impl Default for BellSettings {
+    fn default() -> Self {
+        Self {
+            style: BellStyle::Off,
+            on_lock: false,
+            on_clear: true,
+            on_warning: true,
+        }
+    }
+}
+
+/// Lightweight feedback for game events, for when a full sound system isn't wanted
+pub struct Bell {
+    settings: BellSettings,
+    flash_frames: u8,
+}
+
+impl Bell {
+    pub const fn new(settings: BellSettings) -> Self {
+        Self {
+            settings,
+            flash_frames: 0,
+        }
+    }
+
+    const fn is_enabled(&self, event: BellEvent) -> bool {
+        match event {
+            BellEvent::Lock => self.settings.on_lock,
+            BellEvent::Clear => self.settings.on_clear,
+            BellEvent::Warning => self.settings.on_warning,
+        }
+    }
+
+    /// Ring the bell once if any of the events that just happened are enabled
+    pub fn ring(&mut self, events: &[BellEvent]) {
+        if !events.iter().any(|event| self.is_enabled(*event)) {
+            return;
+        }
+
+        match self.settings.style {
+            BellStyle::Off => (),
+            BellStyle::Terminal => {
+                let mut stdout = stdout();
+                // The bell is a nicety, so failing to ring it isn't worth stopping the game over
+                let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+            }
+            BellStyle::Visual => self.flash_frames = FLASH_LENGTH,
+        }
+    }
+
+    /// Returns true while the walls should be drawn highlighted
+    pub const fn is_flashing(&self) -> bool {
+        self.flash_frames > 0
+    }
+
+    pub const fn frame(&mut self) {
+        self.flash_frames = self.flash_frames.saturating_sub(1);
+    }
+}
@@ -3,60 +3,214 @@ use gemini_engine::{
     core::Vec2D,
 };
 mod blocks;
-pub use blocks::{block_manipulation as tetris_core, Block, BlockType};
-use rand::Rng;
+use super::{messages::Message, rules::Randomizer, PieceColours};
+pub use blocks::{block_manipulation as tetris_core, rotation_system, Block, BlockType};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rotation_system::RotationSystem;
+use std::collections::VecDeque;
+use tetris_core::RotationAttempt;
+
+/// The columns pieces can spawn in during frenzy, tried in order until one is clear of the other
+/// live pieces
+const FRENZY_SPAWN_COLUMNS: [i64; 3] = [5, 2, 8];
+
+/// The number of pieces at the start of a game covered by its sequence hash
+const SEQUENCE_HASH_PIECES: usize = 50;
+
+/// A piece falling alongside the active block in frenzy, waiting to be swapped into control
+#[derive(Debug, Clone)]
+pub struct LivePiece {
+    pub block: Block,
+    pub placing_cooldown: u32,
+    pub gravity_progress: f32,
+}
+
+impl LivePiece {
+    /// Move the newly dealt piece to a spawn column where it doesn't overlap the other live
+    /// pieces
+    ///
+    /// Returns false if there is no room for it
+    pub fn move_clear_of(&mut self, live_pieces: &PixelContainer) -> bool {
+        move_clear_of(&mut self.block, live_pieces)
+    }
+}
+
+/// Move a newly spawned block to a spawn column where it doesn't overlap the other live pieces
+///
+/// Returns false if there is no room for it
+fn move_clear_of(block: &mut Block, live_pieces: &PixelContainer) -> bool {
+    for x in FRENZY_SPAWN_COLUMNS {
+        block.pos.x = x;
+        if !live_pieces.overlaps_element(block) {
+            return true;
+        }
+    }
+
+    false
+}
 
 pub struct BlockManager {
+    /// Upcoming pieces, with the next one at the back
     bag: Vec<BlockType>,
+    /// Picks the upcoming pieces, so a seeded game always deals the same sequence
+    rng: StdRng,
     pub block: Block,
     pub ghost_block: Block,
-    pub held_piece: Option<BlockType>,
-    pub has_held: bool,
+    /// Held pieces, oldest first
+    pub held_pieces: VecDeque<BlockType>,
+    /// The number of times the active block has been held
+    pub times_held: usize,
     pub placing_cooldown: u32,
+    /// The kicks tried by the active block's last rotation
+    pub last_rotation: Option<RotationAttempt>,
     // Constants
     piece_preview_count: usize,
     block_place_cooldown: u32,
+    hold_slots: usize,
+    randomizer: Randomizer,
+    rotation_system: &'static dyn RotationSystem,
+    piece_colours: PieceColours,
 }
 
 impl BlockManager {
-    pub fn new(block_place_cooldown: u32, piece_preview_count: usize) -> Self {
+    pub fn new(
+        block_place_cooldown: u32,
+        piece_preview_count: usize,
+        hold_slots: usize,
+        randomizer: Randomizer,
+        rotation_system: &'static dyn RotationSystem,
+        piece_colours: PieceColours,
+        seed: Option<u64>,
+    ) -> Self {
+        let mut rng =
+            seed.map_or_else(|| StdRng::from_rng(&mut rand::rng()), StdRng::seed_from_u64);
+        let bag = match randomizer {
+            Randomizer::Bag => {
+                let count = rng.random_range(1..8);
+                BlockType::bag(&mut rng)[0..count].to_vec()
+            }
+            Randomizer::Classic => vec![BlockType::classic_roll(None, &mut rng)],
+        };
+
         let mut tmp = Self {
-            bag: BlockType::bag()[0..rand::rng().random_range(1..8)].to_vec(),
+            bag,
+            rng,
             block: Block::DEFAULT,
             ghost_block: Block::DEFAULT,
-            held_piece: None,
-            has_held: false,
+            held_pieces: VecDeque::with_capacity(hold_slots + 1),
+            times_held: 0,
             placing_cooldown: block_place_cooldown,
+            last_rotation: None,
             block_place_cooldown,
             piece_preview_count,
+            hold_slots,
+            randomizer,
+            rotation_system,
+            piece_colours,
         };
         tmp.generate_new_block();
         tmp
     }
 
+    /// Create a block that rotates using the ruleset's rotation system, in the player's colour
+    /// for it
+    fn new_block(&self, shape: BlockType) -> Block {
+        let mut block = Block::with_rotation_system(shape, self.rotation_system);
+        block.colour_override = self.piece_colours.get(shape);
+        block.pattern = self.piece_colours.pattern(shape);
+        block
+    }
+
+    /// Change how long blocks can rest on the ground from now on, e.g. after levelling up
+    pub const fn set_block_place_cooldown(&mut self, block_place_cooldown: u32) {
+        self.block_place_cooldown = block_place_cooldown;
+    }
+
     pub fn reset_placing_cooldown(&mut self) {
         self.placing_cooldown = self.block_place_cooldown;
     }
 
+    /// How many frames a block rests on the ground before it locks
+    pub const fn place_cooldown(&self) -> u32 {
+        self.block_place_cooldown
+    }
+
     /// Call when a block is placed
     ///
     /// Returns true if the block is placed at the very top of the board, indicating a lost game
     pub fn reset(&mut self) -> bool {
         self.reset_placing_cooldown();
-        self.has_held = false;
+        self.times_held = 0;
         self.block.pos.y < 1
     }
 
     pub fn generate_new_block(&mut self) {
+        self.block = self.deal();
+        self.last_rotation = None;
+    }
+
+    /// Deal the next piece to fall alongside the active block in frenzy, in place of one of the
+    /// other live pieces
+    pub fn deal_live_piece(&mut self) -> LivePiece {
+        LivePiece {
+            block: self.deal(),
+            placing_cooldown: self.block_place_cooldown,
+            gravity_progress: 0.0,
+        }
+    }
+
+    /// Take the next piece from the queue, dealing more to keep the previews full
+    fn deal(&mut self) -> Block {
         let next_piece = self.bag.pop().unwrap_or_else(|| unreachable!());
-        if self.bag.len() <= self.piece_preview_count {
-            let mut new_bag = BlockType::bag().to_vec();
-            new_bag.extend(&self.bag);
-            self.bag.clear();
-            self.bag.extend(new_bag);
+        self.fill_queue(self.piece_preview_count + 1, Some(next_piece));
+
+        self.new_block(next_piece)
+    }
+
+    /// Deal more pieces until at least `count` are waiting. New pieces go to the front, since
+    /// pieces are taken from the back. `last_dealt` is the piece taken from the queue just before,
+    /// which the classic randomizer rolls against if the queue is empty
+    fn fill_queue(&mut self, count: usize, last_dealt: Option<BlockType>) {
+        while self.bag.len() < count {
+            match self.randomizer {
+                Randomizer::Bag => {
+                    let mut new_bag = BlockType::bag(&mut self.rng).to_vec();
+                    new_bag.extend(&self.bag);
+                    self.bag = new_bag;
+                }
+                Randomizer::Classic => {
+                    let previous = self.bag.first().copied().or(last_dealt);
+                    self.bag
+                        .insert(0, BlockType::classic_roll(previous, &mut self.rng));
+                }
+            }
         }
+    }
 
-        self.block = Block::new(next_piece);
+    /// The next `count` pieces, in the order they will be dealt
+    pub fn upcoming_pieces(&mut self, count: usize) -> Vec<BlockType> {
+        // The queue is never empty once the first piece has been dealt
+        self.fill_queue(count, None);
+        self.bag.iter().rev().take(count).copied().collect()
+    }
+
+    /// A short hash of the first pieces of the game, so players racing with the same seed can
+    /// check they'll be dealt the same sequence. Only covers the start of the game if called
+    /// before any pieces are played
+    pub fn sequence_hash(&mut self) -> String {
+        let mut pieces = vec![self.block.shape];
+        pieces.extend(self.upcoming_pieces(SEQUENCE_HASH_PIECES - 1));
+
+        // FNV-1a, which unlike the standard library's hasher won't change between Rust versions
+        let hash = pieces.iter().fold(0x811c_9dc5_u32, |hash, piece| {
+            (hash ^ u32::from(piece.letter())).wrapping_mul(0x0100_0193)
+        });
+        format!("{hash:08x}")
+    }
+
+    /// Every piece dealt so far that hasn't been played yet, in the order they will be dealt
+    pub fn queue(&self) -> Vec<BlockType> {
+        self.bag.iter().rev().copied().collect()
     }
 
     /// Attempt to move the block. Resets the placing cooldown and returns true if successful
@@ -68,26 +222,67 @@ impl BlockManager {
         did_move
     }
 
-    /// Attempt to rotate the block. Resets the placing cooldown and returns true if successful
+    /// Move a newly spawned block to a spawn column where it doesn't overlap the other live pieces
+    ///
+    /// Returns false if there is no room for it
+    pub fn move_clear_of(&mut self, live_pieces: &PixelContainer) -> bool {
+        move_clear_of(&mut self.block, live_pieces)
+    }
+
+    /// Shift the block towards the column until its leftmost cell is in it, or it's blocked
+    pub fn shift_to_column(&mut self, collision: &CollisionContainer, column: i64) {
+        let rotation_states = self.block.rotation_states();
+        let leftmost = rotation_states[self.block.rotation.rem_euclid(rotation_states.len())]
+            .iter()
+            .map(|cell| cell.x)
+            .min()
+            .unwrap_or(0)
+            + self.block.pos.x;
+
+        let step = Vec2D::new((column - leftmost).signum(), 0);
+        for _ in 0..(column - leftmost).abs() {
+            if !self.try_move_block(collision, step) {
+                break;
+            }
+        }
+    }
+
+    /// Attempt to rotate the block, resetting the placing cooldown if successful
     pub fn try_rotate_block(&mut self, collision: &CollisionContainer, clockwise: bool) {
-        let did_rotate = tetris_core::try_rotate_block(collision, &mut self.block, clockwise);
-        if did_rotate {
+        let attempt = tetris_core::try_rotate_block(collision, &mut self.block, clockwise);
+        if attempt.did_rotate() {
             self.reset_placing_cooldown();
         }
+        self.last_rotation = Some(attempt);
     }
 
-    /// Hold the current block
+    /// Hold the current block. Once every hold slot is full, the oldest held block takes its
+    /// place, so repeated presses cycle through the held blocks
     pub fn hold(&mut self) {
-        if !self.has_held {
-            let current_held_piece = self.held_piece;
-            self.held_piece = Some(self.block.shape);
-            match current_held_piece {
-                Some(piece) => self.block = Block::new(piece),
-                None => {
-                    self.generate_new_block();
-                }
+        if self.times_held < self.hold_slots {
+            self.held_pieces.push_back(self.block.shape);
+            if self.held_pieces.len() > self.hold_slots {
+                let piece = self
+                    .held_pieces
+                    .pop_front()
+                    .unwrap_or_else(|| unreachable!());
+                self.block = self.new_block(piece);
+                self.last_rotation = None;
+            } else {
+                self.generate_new_block();
             }
-            self.has_held = true;
+            self.times_held += 1;
+        }
+    }
+
+    /// The piece that holding would bring into play, or `None` if holding isn't allowed right now
+    pub fn hold_candidate(&self) -> Option<BlockType> {
+        if self.times_held >= self.hold_slots {
+            None
+        } else if self.held_pieces.len() >= self.hold_slots {
+            self.held_pieces.front().copied()
+        } else {
+            self.bag.last().copied()
         }
     }
 
@@ -105,14 +300,19 @@ impl BlockManager {
         &self,
         collision: &impl CanCollide,
         cleared_lines: i64,
-    ) -> Option<(i64, String)> {
+    ) -> Option<(i64, Message)> {
         tetris_core::handle_t_spin(collision, &self.block, cleared_lines)
     }
 
     pub fn next_piece_display(&self) -> PixelContainer {
+        self.next_piece_display_limited(self.piece_preview_count)
+    }
+
+    /// Display at most `count` of the next pieces, for layouts without room for all of them
+    pub fn next_piece_display_limited(&self, count: usize) -> PixelContainer {
         let mut container = PixelContainer::new();
-        for i in 0..self.piece_preview_count {
-            let mut next_block_display = Block::new(self.bag[self.bag.len() - i - 1]);
+        for i in 0..count.min(self.piece_preview_count) {
+            let mut next_block_display = self.new_block(self.bag[self.bag.len() - i - 1]);
             next_block_display.pos = Vec2D::new(15, 12 + i as i64 * 3);
             container.draw(&next_block_display);
         }
@@ -120,13 +320,51 @@ impl BlockManager {
         container
     }
 
-    pub const fn held_piece_display(&self) -> Option<Block> {
-        if let Some(piece) = self.held_piece {
-            let mut held_block_display = Block::new(piece);
-            held_block_display.pos = Vec2D::new(15, 4);
-            Some(held_block_display)
-        } else {
-            None
+    /// Display the held pieces side by side, oldest first. Returns `None` if nothing is held
+    pub fn held_pieces_display(&self) -> Option<PixelContainer> {
+        self.held_pieces_display_limited(self.held_pieces.len())
+    }
+
+    /// Display at most `count` of the held pieces, oldest first, for layouts without room for all
+    /// of them
+    pub fn held_pieces_display_limited(&self, count: usize) -> Option<PixelContainer> {
+        if self.held_pieces.is_empty() {
+            return None;
         }
+
+        let mut container = PixelContainer::new();
+        for (i, piece) in self.held_pieces.iter().take(count).enumerate() {
+            let mut held_block_display = self.new_block(*piece);
+            held_block_display.pos = Vec2D::new(15 + i as i64 * 4, 4);
+            container.draw(&held_block_display);
+        }
+
+        Some(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rotation_system::Srs;
+
+    #[test]
+    fn the_classic_randomizer_rolls_the_second_piece_against_the_first() {
+        // The reroll makes a repeat about 1 in 50, where an independent roll would be 1 in 7
+        let repeats = (0..500)
+            .filter(|&seed| {
+                let block_manager = BlockManager::new(
+                    30,
+                    1,
+                    0,
+                    Randomizer::Classic,
+                    &Srs,
+                    PieceColours::default(),
+                    Some(seed),
+                );
+                block_manager.queue().first() == Some(&block_manager.block.shape)
+            })
+            .count();
+        assert!(repeats < 25, "{repeats} of 500 games started with a repeat");
     }
 }
@@ -1,10 +1,13 @@
-use gemini_engine::core::{CanDraw, ColChar, Vec2D};
-use rand::seq::SliceRandom;
+use gemini_engine::core::{CanDraw, ColChar, Colour, Vec2D};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 mod block_data;
 use block_data::BlockData;
 pub mod block_manipulation;
+pub mod rotation_system;
+use rotation_system::{RotationSystem, Srs};
 
 const fn bool_to_polarity(value: bool) -> isize {
     if value {
@@ -14,7 +17,7 @@ const fn bool_to_polarity(value: bool) -> isize {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BlockType {
     I,
     J,
@@ -26,7 +29,7 @@ pub enum BlockType {
 }
 
 impl BlockType {
-    const ALL_VARIANTS: [Self; 7] = [
+    pub const ALL_VARIANTS: [Self; 7] = [
         Self::I,
         Self::J,
         Self::L,
@@ -35,21 +38,62 @@ impl BlockType {
         Self::T,
         Self::Z,
     ];
-    pub fn bag() -> [Self; 7] {
+    pub fn bag(rng: &mut impl Rng) -> [Self; 7] {
         let mut variants = Self::ALL_VARIANTS;
-        variants.shuffle(&mut rand::rng());
+        variants.shuffle(rng);
         variants
     }
 
-    fn get_rotation_states(self) -> Vec<Vec<Vec2D>> {
-        BlockData::from(self).rotation_states
+    /// Pick a piece the way the NES version does: roll one of the seven pieces or a dud, and roll
+    /// again if that was the dud or the previous piece
+    pub fn classic_roll(previous: Option<Self>, rng: &mut impl Rng) -> Self {
+        match Self::ALL_VARIANTS.get(rng.random_range(0..8)) {
+            Some(&piece) if Some(piece) != previous => piece,
+            _ => Self::ALL_VARIANTS[rng.random_range(0..7)],
+        }
+    }
+
+    fn get_rotation_states(self) -> &'static [Vec<Vec2D>] {
+        &BlockData::get(self).rotation_states
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL_VARIANTS
+            .into_iter()
+            .find(|piece| name.eq_ignore_ascii_case(&piece.letter().to_string()))
     }
-    fn get_colour(self) -> ColChar {
-        // ColChar::EMPTY.with_char('▒') // Colourless
-        ColChar::SOLID.with_colour(BlockData::from(self).colour)
+
+    /// The letter the piece is named after
+    pub const fn letter(self) -> char {
+        match self {
+            Self::I => 'I',
+            Self::J => 'J',
+            Self::L => 'L',
+            Self::O => 'O',
+            Self::S => 'S',
+            Self::T => 'T',
+            Self::Z => 'Z',
+        }
     }
-    pub(super) fn get_wall_kick_data(self) -> HashMap<(usize, usize), Vec<Vec2D>> {
-        BlockData::from(self).wall_kick_data
+
+    /// The character the piece's cells are filled with when piece patterns are turned on, so
+    /// pieces can be told apart without colour
+    pub const fn pattern(self) -> char {
+        match self {
+            Self::I => '▓',
+            Self::J => '▒',
+            Self::L => '░',
+            Self::O => '█',
+            Self::S => '#',
+            Self::T => '@',
+            Self::Z => '%',
+        }
+    }
+
+    pub fn default_colour(self) -> Colour {
+        BlockData::get(self).colour
+    }
+    fn get_wall_kick_data(self) -> &'static HashMap<(usize, usize), Vec<Vec2D>> {
+        &BlockData::get(self).wall_kick_data
     }
 }
 
@@ -58,6 +102,11 @@ pub struct Block {
     pub pos: Vec2D,
     pub shape: BlockType,
     pub rotation: usize,
+    pub rotation_system: &'static dyn RotationSystem,
+    /// The colour to draw the block in instead of its shape's default colour
+    pub colour_override: Option<Colour>,
+    /// The character to fill the block's cells with instead of a solid block
+    pub pattern: Option<char>,
     pub(super) is_ghost: bool,
 }
 
@@ -65,16 +114,30 @@ impl Block {
     pub const DEFAULT: Self = Self::new(BlockType::O);
 
     pub const fn new(shape: BlockType) -> Self {
+        Self::with_rotation_system(shape, &Srs)
+    }
+
+    pub const fn with_rotation_system(
+        shape: BlockType,
+        rotation_system: &'static dyn RotationSystem,
+    ) -> Self {
         Self {
             pos: Vec2D::new(5, 0),
             shape,
             rotation: 0,
+            rotation_system,
+            colour_override: None,
+            pattern: None,
             is_ghost: false,
         }
     }
 
+    pub fn rotation_states(&self) -> &'static [Vec<Vec2D>] {
+        self.rotation_system.rotation_states(self.shape)
+    }
+
     fn rot_state_len(&self) -> isize {
-        self.shape.get_rotation_states().len() as isize
+        self.rotation_states().len() as isize
     }
     pub fn get_rotation_indexes(&self, clockwise: bool) -> (usize, usize) {
         (
@@ -95,6 +158,9 @@ impl Clone for Block {
             pos: self.pos,
             shape: self.shape,
             rotation: self.rotation,
+            rotation_system: self.rotation_system,
+            colour_override: self.colour_override,
+            pattern: self.pattern,
             is_ghost: false,
         }
     }
@@ -102,11 +168,17 @@ impl Clone for Block {
 
 impl CanDraw for Block {
     fn draw_to(&self, canvas: &mut impl gemini_engine::core::Canvas) {
-        let rotation_states = self.shape.get_rotation_states();
+        let rotation_states = self.rotation_states();
         let block_colour = if self.is_ghost {
             ColChar::BACKGROUND
         } else {
-            self.shape.get_colour()
+            // ColChar::EMPTY.with_char('▒') // Colourless
+            ColChar::SOLID
+                .with_char(self.pattern.unwrap_or(ColChar::SOLID.text_char))
+                .with_colour(
+                    self.colour_override
+                        .unwrap_or_else(|| self.shape.default_colour()),
+                )
         };
 
         rotation_states[self.rotation.rem_euclid(rotation_states.len())]
@@ -1,14 +1,26 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::LazyLock};
 
 use super::BlockType;
 use gemini_engine::core::{Colour, Vec2D};
 
+/// The data for every shape, built once so drawing and rotating blocks don't rebuild it every frame
+static BLOCK_DATA: LazyLock<HashMap<BlockType, BlockData>> = LazyLock::new(|| {
+    BlockType::ALL_VARIANTS
+        .into_iter()
+        .map(|shape| (shape, BlockData::from(shape)))
+        .collect()
+});
+
 pub(super) struct BlockData {
     pub rotation_states: Vec<Vec<Vec2D>>,
     pub colour: Colour,
     pub wall_kick_data: HashMap<(usize, usize), Vec<Vec2D>>,
 }
 impl BlockData {
+    pub fn get(shape: BlockType) -> &'static Self {
+        &BLOCK_DATA[&shape]
+    }
+
     const fn new(
         rotation_states: Vec<Vec<Vec2D>>,
         colour: Colour,
@@ -4,6 +4,7 @@ use gemini_engine::{
 };
 
 use super::{Block, BlockType};
+use crate::game::messages::Message;
 
 pub fn try_move_block(collision: &CollisionContainer, block: &mut Block, offset: Vec2D) -> bool {
     let did_move = !collision.will_overlap_element(block, offset);
@@ -14,38 +15,83 @@ pub fn try_move_block(collision: &CollisionContainer, block: &mut Block, offset:
     did_move
 }
 
+/// Push a block up out of anything it overlaps, such as a stack that settled into it
+///
+/// Returns false if it reaches the top of the board without finding room
+pub fn lift_clear_of(collision: &CollisionContainer, block: &mut Block) -> bool {
+    while collision.overlaps_element(block) {
+        if block.pos.y < 0 {
+            return false;
+        }
+        block.pos.y -= 1;
+    }
+
+    true
+}
+
+/// The kick offsets tried while rotating a block, for showing how the rotation system behaved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationAttempt {
+    /// The block's rotation centre before it was rotated
+    pub origin: Vec2D,
+    /// Every offset tried, in order
+    pub kicks: Vec<Vec2D>,
+    /// The index of the offset the block was moved by, if any fit
+    pub accepted: Option<usize>,
+}
+
+impl RotationAttempt {
+    pub const fn did_rotate(&self) -> bool {
+        self.accepted.is_some()
+    }
+}
+
 pub fn try_rotate_block(
     collision: &CollisionContainer,
     block: &mut Block,
     clockwise: bool,
-) -> bool {
+) -> RotationAttempt {
+    let mut attempt = RotationAttempt {
+        origin: block.pos,
+        kicks: Vec::new(),
+        accepted: None,
+    };
     if block.shape == BlockType::O {
-        return false;
+        return attempt;
     }
 
     let rotation_index = block.get_rotation_indexes(clockwise);
     let mut hypothetical_block = block.clone();
     hypothetical_block.rotate(clockwise);
 
-    let mut did_move = false;
-    for possible_offset in &block.shape.get_wall_kick_data()[&rotation_index] {
+    // The cells of the rotated block that are in the way if it isn't kicked
+    let blocked: Vec<Vec2D> = block.rotation_states()[rotation_index.1]
+        .iter()
+        .filter(|cell| collision.collides_with_pos(block.pos + **cell))
+        .copied()
+        .collect();
+
+    attempt.kicks = block
+        .rotation_system
+        .kicks(block.shape, rotation_index, &blocked);
+    for (i, possible_offset) in attempt.kicks.iter().enumerate() {
         hypothetical_block.pos = block.pos + *possible_offset;
         if !collision.overlaps_element(&hypothetical_block) {
-            did_move = true;
+            attempt.accepted = Some(i);
             block.pos += *possible_offset;
             block.rotate(clockwise);
             break;
         }
     }
 
-    did_move
+    attempt
 }
 
 pub fn handle_t_spin(
     collision: &impl CanCollide,
     block: &Block,
     cleared_lines: i64,
-) -> Option<(i64, String)> {
+) -> Option<(i64, Message)> {
     if block.shape == BlockType::T {
         let positions_to_check: Vec<Vec2D> = [
             Vec2D::new(1, 1),   // Top-left
@@ -75,13 +121,19 @@ pub fn handle_t_spin(
         };
 
         if counted_positions > 2 && (blocked_from_top_left || blocked_from_top_right) {
-            Some(match cleared_lines {
-                0 => (400, String::from("T-Spin!")),
-                1 => (800, String::from("T-Spin Single!")),
-                2 => (1200, String::from("T-Spin Double!")),
-                3 => (1600, String::from("T-Spin Triple!")),
-                _ => (200, String::from("T-Spin?")),
-            })
+            let score = match cleared_lines {
+                0 => 400,
+                1 => 800,
+                2 => 1200,
+                3 => 1600,
+                _ => 200,
+            };
+            Some((
+                score,
+                Message::TSpin {
+                    lines: cleared_lines,
+                },
+            ))
         } else {
             None
         }
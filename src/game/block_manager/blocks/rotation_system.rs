@@ -0,0 +1,149 @@
+use gemini_engine::core::Vec2D;
+use std::{collections::HashMap, fmt, sync::LazyLock};
+
+use super::BlockType;
+
+/// The rules deciding how pieces are shaped in each rotation state and how they are kicked off
+/// walls and other blocks when rotated
+pub trait RotationSystem: fmt::Debug + Sync {
+    /// The name the rotation system is chosen by, as accepted by [`from_name`]
+    fn name(&self) -> &'static str;
+
+    /// The cells covered by the piece in each of its rotation states, relative to its position.
+    /// The first state is the one the piece spawns in
+    fn rotation_states(&self, shape: BlockType) -> &'static [Vec<Vec2D>];
+
+    /// The offsets to try in order when rotating between the `(from, to)` states, where `blocked`
+    /// are the cells of the rotated piece that overlap something if it isn't moved
+    fn kicks(&self, shape: BlockType, rotation: (usize, usize), blocked: &[Vec2D]) -> Vec<Vec2D>;
+}
+
+pub fn from_name(name: &str) -> Option<&'static dyn RotationSystem> {
+    match name.to_lowercase().as_str() {
+        "srs" => Some(&Srs),
+        "ars" => Some(&Ars),
+        _ => None,
+    }
+}
+
+/// Save rotation systems by name, for `#[serde(with = "by_name")]`
+pub mod by_name {
+    use super::RotationSystem;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        system: &&'static dyn RotationSystem,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(system.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static dyn RotationSystem, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::from_name(&name)
+            .ok_or_else(|| D::Error::custom(format!("unknown rotation system '{name}'")))
+    }
+}
+
+/// The Super Rotation System used by modern games
+#[derive(Debug)]
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn name(&self) -> &'static str {
+        "srs"
+    }
+
+    fn rotation_states(&self, shape: BlockType) -> &'static [Vec<Vec2D>] {
+        shape.get_rotation_states()
+    }
+
+    fn kicks(&self, shape: BlockType, rotation: (usize, usize), _blocked: &[Vec2D]) -> Vec<Vec2D> {
+        shape
+            .get_wall_kick_data()
+            .get(&rotation)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// The Arika Rotation System from the arcade games. Pieces spawn flat side up and sit at the
+/// bottom of their bounding box in every state. Rotations are kicked one cell right then one cell
+/// left, except for the I piece, which never kicks
+#[derive(Debug)]
+pub struct Ars;
+
+/// The ARS rotation states of every shape, built once
+static ARS_ROTATION_STATES: LazyLock<HashMap<BlockType, Vec<Vec<Vec2D>>>> = LazyLock::new(|| {
+    BlockType::ALL_VARIANTS
+        .into_iter()
+        .map(|shape| (shape, ars_rotation_states(shape)))
+        .collect()
+});
+
+fn ars_rotation_states(shape: BlockType) -> Vec<Vec<Vec2D>> {
+    let states: &[[(i64, i64); 4]] = match shape {
+        BlockType::O => return shape.get_rotation_states().to_vec(),
+        BlockType::I => &[
+            [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            [(1, -1), (1, 0), (1, 1), (1, 2)],
+        ],
+        BlockType::T => &[
+            [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            [(0, -1), (-1, 0), (0, 0), (0, 1)],
+            [(0, 0), (-1, 1), (0, 1), (1, 1)],
+            [(0, -1), (0, 0), (1, 0), (0, 1)],
+        ],
+        BlockType::J => &[
+            [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            [(0, -1), (0, 0), (-1, 1), (0, 1)],
+            [(-1, 0), (-1, 1), (0, 1), (1, 1)],
+            [(0, -1), (1, -1), (0, 0), (0, 1)],
+        ],
+        BlockType::L => &[
+            [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            [(-1, -1), (0, -1), (0, 0), (0, 1)],
+            [(1, 0), (-1, 1), (0, 1), (1, 1)],
+            [(0, -1), (0, 0), (0, 1), (1, 1)],
+        ],
+        BlockType::S => &[
+            [(0, 0), (1, 0), (-1, 1), (0, 1)],
+            [(-1, -1), (-1, 0), (0, 0), (0, 1)],
+        ],
+        BlockType::Z => &[
+            [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            [(1, -1), (0, 0), (1, 0), (0, 1)],
+        ],
+    };
+
+    states
+        .iter()
+        .map(|state| state.iter().map(|&(x, y)| Vec2D::new(x, y)).collect())
+        .collect()
+}
+
+impl RotationSystem for Ars {
+    fn name(&self) -> &'static str {
+        "ars"
+    }
+
+    fn rotation_states(&self, shape: BlockType) -> &'static [Vec<Vec2D>] {
+        &ARS_ROTATION_STATES[&shape]
+    }
+
+    fn kicks(&self, shape: BlockType, _rotation: (usize, usize), blocked: &[Vec2D]) -> Vec<Vec2D> {
+        // J, L and T pieces don't kick if the first blocked cell, reading from the top left, is
+        // in the middle column
+        let first_blocked = blocked.iter().min_by_key(|cell| (cell.y, cell.x));
+        let blocked_in_centre = matches!(shape, BlockType::J | BlockType::L | BlockType::T)
+            && first_blocked.is_some_and(|cell| cell.x == 0);
+
+        if shape == BlockType::I || blocked_in_centre {
+            vec![Vec2D::ZERO]
+        } else {
+            vec![Vec2D::ZERO, Vec2D::new(1, 0), Vec2D::new(-1, 0)]
+        }
+    }
+}
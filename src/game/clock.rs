@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+/// Measures the official game time, which stands still while the game is paused or waiting in a
+/// menu, alongside the real time since the game started
+#[derive(Debug, Clone, Copy)]
+pub struct GameClock {
+    /// When the game started, or `None` if it hasn't yet
+    started: Option<Instant>,
+    /// Time spent stopped, not counting the current stop
+    stopped_time: Duration,
+    /// When the clock was stopped, if it's stopped now
+    stopped_at: Option<Instant>,
+}
+
+impl GameClock {
+    /// A clock running from now
+    pub fn start() -> Self {
+        Self {
+            started: Some(Instant::now()),
+            stopped_time: Duration::ZERO,
+            stopped_at: None,
+        }
+    }
+
+    /// A clock that hasn't started yet, which stays at zero until it's replaced with a running one
+    pub fn stopped() -> Self {
+        Self {
+            started: None,
+            stopped_time: Duration::ZERO,
+            stopped_at: Some(Instant::now()),
+        }
+    }
+
+    /// Stop the game time, e.g. when pausing. Does nothing if it's already stopped
+    pub fn stop(&mut self) {
+        self.stopped_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Carry on counting the game time after it was stopped
+    pub fn resume(&mut self) {
+        if let Some(stopped_at) = self.stopped_at.take() {
+            self.stopped_time += stopped_at.elapsed();
+        }
+    }
+
+    /// Time spent playing, measured by the system's monotonic clock so dropped frames don't slow
+    /// it down
+    pub fn game_time(&self) -> Duration {
+        let Some(started) = self.started else {
+            return Duration::ZERO;
+        };
+        let end = self.stopped_at.unwrap_or_else(Instant::now);
+        end.duration_since(started)
+            .saturating_sub(self.stopped_time)
+    }
+
+    /// Time since the game started, including any time spent paused. Zero if it hasn't started
+    pub fn real_time(&self) -> Duration {
+        self.started
+            .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    const STEP: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn a_clock_that_never_started_stays_at_zero() {
+        let mut clock = GameClock::stopped();
+        sleep(STEP);
+        clock.resume();
+        sleep(STEP);
+
+        assert_eq!(clock.game_time(), Duration::ZERO);
+        assert_eq!(clock.real_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn a_stopped_clock_stands_still() {
+        let mut clock = GameClock::start();
+        sleep(STEP);
+        clock.stop();
+        let stopped_time = clock.game_time();
+        sleep(STEP);
+
+        assert!(stopped_time >= STEP);
+        assert_eq!(clock.game_time(), stopped_time);
+        assert!(clock.real_time() >= stopped_time + STEP);
+    }
+
+    #[test]
+    fn a_resumed_clock_leaves_out_the_time_it_was_stopped() {
+        let mut clock = GameClock::start();
+        sleep(STEP);
+        clock.stop();
+        sleep(STEP * 5);
+        clock.resume();
+        // Stopping again doesn't lose the earlier stop
+        clock.stop();
+        clock.resume();
+        sleep(STEP);
+
+        let game_time = clock.game_time();
+        assert!(game_time >= STEP * 2);
+        assert!(clock.real_time() >= game_time + STEP * 5);
+    }
+}
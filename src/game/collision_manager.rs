@@ -1,43 +1,115 @@
 use gemini_engine::{
     containers::{CollisionContainer, PixelContainer},
-    core::{CanDraw, ColChar, Vec2D},
-    primitives::Rect,
+    core::{CanDraw, ColChar, Colour, Vec2D},
+    primitives::{Pixel, Rect},
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+/// Stacks reaching this many rows from the top of the board are in danger of topping out
+const DANGER_ZONE_HEIGHT: i64 = 4;
+
+/// The chance of each cell of a locked block cracking with the contamination modifier
+const CRACK_CHANCE: f64 = 0.08;
+
+/// The chance of each cell of a locked block turning into a bomb with the contamination modifier
+const BOMB_CHANCE: f64 = 0.02;
 
-pub fn generate_borders() -> PixelContainer {
+pub fn generate_borders(wall_char: ColChar) -> PixelContainer {
     let mut borders = PixelContainer::new();
     borders.draw(&Rect::new(
         // Left wall
         Vec2D::new(0, 0),
         Vec2D::new(1, 21),
-        ColChar::SOLID,
+        wall_char,
     ));
     borders.draw(&Rect::new(
         // Right wall
         Vec2D::new(11, 0),
         Vec2D::new(1, 21),
-        ColChar::SOLID,
+        wall_char,
     ));
     borders.draw(&Rect::new(
         // Floor
         Vec2D::new(1, 20),
         Vec2D::new(10, 1),
-        ColChar::SOLID,
+        wall_char,
     ));
 
     borders
 }
 
+/// The appearance of garbage rows, shaded differently from the player's own pieces
+fn garbage_char() -> ColChar {
+    ColChar::EMPTY
+        .with_char('▓')
+        .with_colour(Colour::rgb(128, 128, 128))
+}
+
+/// The appearance of the holes in garbage rows, so they stand out from the gaps in the stack
+fn garbage_hole_char() -> ColChar {
+    ColChar::EMPTY
+        .with_char('·')
+        .with_colour(Colour::rgb(96, 96, 96))
+}
+
+/// The appearance of cracked cells, which are left behind as plain blocks the first time their
+/// row is cleared
+pub fn cracked_char() -> ColChar {
+    ColChar::EMPTY
+        .with_char('▒')
+        .with_colour(Colour::rgb(200, 200, 200))
+}
+
+/// The appearance of what's left of a cracked cell after its row was cleared once
+fn broken_char() -> ColChar {
+    ColChar::SOLID.with_colour(Colour::rgb(200, 200, 200))
+}
+
+/// The appearance of bomb cells, which clear the cells around them when their row is cleared
+pub fn bomb_char() -> ColChar {
+    ColChar::EMPTY
+        .with_char('*')
+        .with_colour(Colour::rgb(255, 64, 0))
+}
+
+/// Stationary blocks that behave differently from the plain cells of a locked piece. Kept apart
+/// from the blocks' appearance, since piece patterns can draw a plain cell with the same character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellKind {
+    /// Left behind as a plain block the first time its row is cleared
+    Cracked,
+    /// Clears the cells around it when its row is cleared
+    Bomb,
+    /// Part of a garbage row, rather than a piece the player placed
+    Garbage,
+}
+
 pub struct CollisionManager {
     pub game_boundaries: PixelContainer,
     pub stationary_blocks: PixelContainer,
+    /// Which locked piece each stationary block came from, so the pieces can be told apart once
+    /// they're part of the stack. Garbage doesn't belong to a piece
+    piece_ids: HashMap<Vec2D, u32>,
+    /// The kind of each stationary block that isn't a plain cell
+    cell_kinds: HashMap<Vec2D, CellKind>,
+    /// The ID given to the next locked piece
+    next_piece_id: u32,
 }
 
 impl CollisionManager {
     pub fn new() -> Self {
         Self {
-            game_boundaries: generate_borders(),
+            game_boundaries: generate_borders(ColChar::SOLID),
             stationary_blocks: PixelContainer::new(),
+            piece_ids: HashMap::new(),
+            cell_kinds: HashMap::new(),
+            next_piece_id: 0,
         }
     }
 
@@ -48,6 +120,85 @@ impl CollisionManager {
         collision
     }
 
+    /// The kind of the stationary block at the position, if it isn't a plain cell
+    pub fn cell_kind(&self, pos: Vec2D) -> Option<CellKind> {
+        self.cell_kinds.get(&pos).copied()
+    }
+
+    /// Randomly turn some of the cells of a block about to be locked into cracked cells or bombs
+    pub fn contaminate(&mut self, cells: &mut [Pixel], rng: &mut impl Rng) {
+        for cell in cells {
+            let roll: f64 = rng.random();
+            let (kind, fill_char) = if roll < BOMB_CHANCE {
+                (CellKind::Bomb, bomb_char())
+            } else if roll < BOMB_CHANCE + CRACK_CHANCE {
+                (CellKind::Cracked, cracked_char())
+            } else {
+                continue;
+            };
+            cell.fill_char = fill_char;
+            self.cell_kinds.insert(cell.pos, kind);
+        }
+    }
+
+    /// Returns true if any stationary block is within the top rows of the board
+    pub fn is_in_danger(&self) -> bool {
+        self.stationary_blocks
+            .pixels
+            .iter()
+            .any(|p| p.pos.y < DANGER_ZONE_HEIGHT)
+    }
+
+    /// Push the stack up and fill the rows beneath it with garbage, leaving gaps at each row's hole
+    /// columns. `hole_rows` are listed from top to bottom
+    pub fn add_garbage_rows(&mut self, hole_rows: &[Vec<i64>]) {
+        let row_count = hole_rows.len() as i64;
+        for pixel in &mut self.stationary_blocks.pixels {
+            pixel.pos.y -= row_count;
+        }
+        self.move_cell_state(|pos| pos.y -= row_count);
+
+        for (i, holes) in hole_rows.iter().enumerate() {
+            let y = 20 - row_count + i as i64;
+            for x in (1..11).filter(|x| !holes.contains(x)) {
+                let pos = Vec2D::new(x, y);
+                self.draw(&Pixel::new(pos, garbage_char()));
+                self.cell_kinds.insert(pos, CellKind::Garbage);
+            }
+        }
+    }
+
+    /// The number of rows that still contain garbage
+    pub fn garbage_rows_left(&self) -> usize {
+        self.garbage_rows().len()
+    }
+
+    /// The rows that still contain garbage
+    fn garbage_rows(&self) -> HashSet<i64> {
+        self.cell_kinds
+            .iter()
+            .filter(|(_, kind)| **kind == CellKind::Garbage)
+            .map(|(pos, _)| pos.y)
+            .collect()
+    }
+
+    /// Markers for the empty cells in rows that still have garbage in them
+    fn garbage_holes(&self) -> PixelContainer {
+        let pixels = &self.stationary_blocks.pixels;
+
+        let mut holes = PixelContainer::new();
+        for y in self.garbage_rows() {
+            for x in 1..11 {
+                let pos = Vec2D::new(x, y);
+                if !pixels.iter().any(|p| p.pos == pos) {
+                    holes.draw(&Pixel::new(pos, garbage_hole_char()));
+                }
+            }
+        }
+
+        holes
+    }
+
     pub fn draw<E: CanDraw>(&mut self, element: &E) {
         self.stationary_blocks.draw(element);
     }
@@ -78,8 +229,30 @@ impl CollisionManager {
             }
 
             cleared_lines += 1;
-            pixels.retain(|p| p.pos.y != y);
+
+            // Bombs in the row blow up the cells around them as well
+            let bombs: Vec<Vec2D> = pixels
+                .iter()
+                .filter(|p| p.pos.y == y && self.cell_kind(p.pos) == Some(CellKind::Bomb))
+                .map(|p| p.pos)
+                .collect();
+            pixels.retain(|p| {
+                !bombs
+                    .iter()
+                    .any(|bomb| (p.pos.x - bomb.x).abs() <= 1 && (p.pos.y - bomb.y).abs() <= 1)
+            });
+
+            // Cracked cells need a second clear to go, so they're left behind the first time
+            pixels.retain(|p| p.pos.y != y || self.cell_kind(p.pos) == Some(CellKind::Cracked));
+            for pixel in pixels.iter_mut().filter(|p| p.pos.y == y) {
+                pixel.fill_char = broken_char();
+                self.cell_kinds.remove(&pixel.pos);
+            }
         }
+        self.piece_ids
+            .retain(|pos, _| pixels.iter().any(|p| p.pos == *pos));
+        self.cell_kinds
+            .retain(|pos, _| pixels.iter().any(|p| p.pos == *pos));
 
         let mut y = max_y + 1;
         loop {
@@ -108,6 +281,11 @@ impl CollisionManager {
                         }
                     })
                     .collect();
+                self.move_cell_state(|pos| {
+                    if pos.y < y {
+                        pos.y += 1;
+                    }
+                });
 
                 y += 1;
                 min_y += 1;
@@ -119,18 +297,303 @@ impl CollisionManager {
         cleared_lines
     }
 
-    /// Add an element to the stationary blocks and clear all full lines
+    /// Let groups of connected blocks that are left floating fall until they land, for cascade
+    /// gravity. Lower groups are settled first so the ones above can land on them
+    ///
+    /// Returns true if anything fell
+    pub fn settle(&mut self) -> bool {
+        let mut anything_fell = false;
+        loop {
+            let mut groups = self.connected_groups();
+            groups.sort_by_key(|group| {
+                Reverse(
+                    group
+                        .iter()
+                        .map(|&i| self.stationary_blocks.pixels[i].pos.y)
+                        .max(),
+                )
+            });
+
+            let mut fell = false;
+            for group in groups {
+                let distance = self.fall_distance(&group);
+                if distance > 0 {
+                    let moved: Vec<(Vec2D, Option<u32>, Option<CellKind>)> = group
+                        .iter()
+                        .map(|&i| {
+                            let pos = self.stationary_blocks.pixels[i].pos;
+                            (
+                                pos,
+                                self.piece_ids.remove(&pos),
+                                self.cell_kinds.remove(&pos),
+                            )
+                        })
+                        .collect();
+                    for (pos, piece_id, kind) in moved {
+                        let pos = pos + Vec2D::new(0, distance);
+                        if let Some(piece_id) = piece_id {
+                            self.piece_ids.insert(pos, piece_id);
+                        }
+                        if let Some(kind) = kind {
+                            self.cell_kinds.insert(pos, kind);
+                        }
+                    }
+                    for &i in &group {
+                        self.stationary_blocks.pixels[i].pos.y += distance;
+                    }
+                    fell = true;
+                }
+            }
+
+            if !fell {
+                return anything_fell;
+            }
+            anything_fell = true;
+        }
+    }
+
+    /// The indices of the stationary blocks, split into groups of blocks touching each other
+    /// above, below or to the side
+    fn connected_groups(&self) -> Vec<Vec<usize>> {
+        let pixels = &self.stationary_blocks.pixels;
+        let mut grouped = vec![false; pixels.len()];
+        let mut groups = Vec::new();
+
+        for start in 0..pixels.len() {
+            if grouped[start] {
+                continue;
+            }
+            grouped[start] = true;
+
+            let mut group = vec![start];
+            let mut next = 0;
+            while let Some(&i) = group.get(next) {
+                next += 1;
+                for (j, pixel) in pixels.iter().enumerate() {
+                    let offset = pixel.pos - pixels[i].pos;
+                    if !grouped[j] && offset.x.abs() + offset.y.abs() == 1 {
+                        grouped[j] = true;
+                        group.push(j);
+                    }
+                }
+            }
+            groups.push(group);
+        }
+
+        groups
+    }
+
+    /// How many rows a group of stationary blocks can fall before landing on the floor or another
+    /// block
+    fn fall_distance(&self, group: &[usize]) -> i64 {
+        let others: HashSet<Vec2D> = self
+            .stationary_blocks
+            .pixels
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !group.contains(i))
+            .map(|(_, pixel)| pixel.pos)
+            .collect();
+
+        let mut distance = 0;
+        while group.iter().all(|&i| {
+            let below = self.stationary_blocks.pixels[i].pos + Vec2D::new(0, distance + 1);
+            below.y < 20 && !others.contains(&below)
+        }) {
+            distance += 1;
+        }
+
+        distance
+    }
+
+    /// Add a locked piece's cells to the stationary blocks and clear all full lines
     ///
     /// Returns the number of cleared lines
-    pub fn draw_and_clear_lines<E: CanDraw>(&mut self, block: &E) -> i64 {
-        self.draw(block);
+    pub fn draw_and_clear_lines(&mut self, cells: &PixelContainer) -> i64 {
+        for pixel in &cells.pixels {
+            self.piece_ids.insert(pixel.pos, self.next_piece_id);
+        }
+        self.next_piece_id = self.next_piece_id.wrapping_add(1);
+
+        self.draw(cells);
         self.clear_filled_lines()
     }
+
+    /// Move every block's piece ID and kind along with the blocks
+    fn move_cell_state(&mut self, move_pos: impl Fn(&mut Vec2D)) {
+        self.piece_ids = self
+            .piece_ids
+            .drain()
+            .map(|(mut pos, piece_id)| {
+                move_pos(&mut pos);
+                (pos, piece_id)
+            })
+            .collect();
+        self.cell_kinds = self
+            .cell_kinds
+            .drain()
+            .map(|(mut pos, kind)| {
+                move_pos(&mut pos);
+                (pos, kind)
+            })
+            .collect();
+    }
+
+    /// Split solid stationary blocks where they touch a block of another piece, so the pieces in
+    /// the stack can be told apart. Drawn over the board at single width, since each side of a
+    /// double width cell needs its own character
+    pub fn piece_borders(&self) -> PixelContainer {
+        let filled: HashSet<Vec2D> = self
+            .stationary_blocks
+            .pixels
+            .iter()
+            .map(|pixel| pixel.pos)
+            .collect();
+        let is_other_piece = |pos: Vec2D, neighbour: Vec2D| {
+            filled.contains(&neighbour)
+                && self.piece_ids.get(&pos) != self.piece_ids.get(&neighbour)
+        };
+
+        let mut borders = PixelContainer::new();
+        for pixel in &self.stationary_blocks.pixels {
+            if pixel.fill_char.text_char != ColChar::SOLID.text_char {
+                continue;
+            }
+
+            let split_right = is_other_piece(pixel.pos, pixel.pos + Vec2D::new(1, 0));
+            let split_below = is_other_piece(pixel.pos, pixel.pos + Vec2D::new(0, 1));
+            // Each half of the cell is split into quadrants, keeping the ones away from the border
+            let (left, right) = match (split_right, split_below) {
+                (false, false) => continue,
+                (true, false) => ('█', '▌'),
+                (false, true) => ('▀', '▀'),
+                (true, true) => ('▀', '▘'),
+            };
+
+            let pos = Vec2D::new(pixel.pos.x * 2, pixel.pos.y);
+            borders.draw(&Pixel::new(pos, pixel.fill_char.with_char(left)));
+            borders.draw(&Pixel::new(
+                pos + Vec2D::new(1, 0),
+                pixel.fill_char.with_char(right),
+            ));
+        }
+
+        borders
+    }
 }
 
 impl CanDraw for CollisionManager {
     fn draw_to(&self, canvas: &mut impl gemini_engine::core::Canvas) {
+        self.garbage_holes().draw_to(canvas);
         self.stationary_blocks.draw_to(canvas);
         self.game_boundaries.draw_to(canvas);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(cells: &[(i64, i64)]) -> CollisionManager {
+        let mut collision_manager = CollisionManager::new();
+        for &(x, y) in cells {
+            collision_manager.draw(&Pixel::new(Vec2D::new(x, y), ColChar::SOLID));
+        }
+        collision_manager
+    }
+
+    fn cells(collision_manager: &CollisionManager) -> HashSet<(i64, i64)> {
+        collision_manager
+            .stationary_blocks
+            .pixels
+            .iter()
+            .map(|pixel| (pixel.pos.x, pixel.pos.y))
+            .collect()
+    }
+
+    #[test]
+    fn floating_groups_fall_as_one() {
+        // An L shape floating over a single block on the floor
+        let mut collision_manager = board(&[(3, 19), (3, 14), (3, 15), (4, 15)]);
+
+        assert!(collision_manager.settle());
+        assert_eq!(
+            cells(&collision_manager),
+            HashSet::from([(3, 19), (3, 17), (3, 18), (4, 18)])
+        );
+        assert!(!collision_manager.settle());
+    }
+
+    #[test]
+    fn settling_can_fill_lines() {
+        // A row missing one cell, with a block floating above the gap
+        let mut row: Vec<(i64, i64)> = (1..11).filter(|x| *x != 5).map(|x| (x, 19)).collect();
+        row.push((5, 10));
+        let mut collision_manager = board(&row);
+
+        assert!(collision_manager.settle());
+        assert_eq!(collision_manager.clear_filled_lines(), 1);
+        assert!(cells(&collision_manager).is_empty());
+    }
+
+    fn piece(cells: &[(i64, i64)]) -> PixelContainer {
+        let mut piece = PixelContainer::new();
+        for &(x, y) in cells {
+            piece.draw(&Pixel::new(Vec2D::new(x, y), ColChar::SOLID));
+        }
+        piece
+    }
+
+    #[test]
+    fn only_cracked_cells_survive_a_clear() {
+        // A row drawn entirely in the cracked cell's character, as the J pattern is, with one
+        // cell that really is cracked
+        let mut collision_manager = CollisionManager::new();
+        for x in 1..11 {
+            collision_manager.draw(&Pixel::new(Vec2D::new(x, 19), cracked_char()));
+        }
+        collision_manager
+            .cell_kinds
+            .insert(Vec2D::new(4, 19), CellKind::Cracked);
+
+        assert_eq!(collision_manager.clear_filled_lines(), 1);
+        assert_eq!(cells(&collision_manager), HashSet::from([(4, 19)]));
+        assert_eq!(collision_manager.cell_kind(Vec2D::new(4, 19)), None);
+    }
+
+    #[test]
+    fn garbage_is_told_apart_from_patterned_pieces() {
+        // An I piece drawn in its pattern, which shares the garbage's character
+        let mut collision_manager = CollisionManager::new();
+        for x in 3..7 {
+            collision_manager.draw(&Pixel::new(Vec2D::new(x, 19), garbage_char()));
+        }
+        assert_eq!(collision_manager.garbage_rows_left(), 0);
+
+        collision_manager.add_garbage_rows(&[vec![1], vec![2]]);
+        assert_eq!(collision_manager.garbage_rows_left(), 2);
+        assert_eq!(
+            collision_manager.cell_kind(Vec2D::new(3, 17)),
+            None,
+            "The I piece should have been pushed up without becoming garbage"
+        );
+    }
+
+    #[test]
+    fn piece_borders_follow_the_stack_down() {
+        let mut collision_manager = CollisionManager::new();
+        collision_manager.draw_and_clear_lines(&piece(&[(1, 18), (2, 18)]));
+        collision_manager.draw_and_clear_lines(&piece(&[(3, 18), (3, 17)]));
+        let full_row: Vec<(i64, i64)> = (1..11).map(|x| (x, 19)).collect();
+        assert_eq!(collision_manager.draw_and_clear_lines(&piece(&full_row)), 1);
+
+        let borders: HashSet<(i64, i64, char)> = collision_manager
+            .piece_borders()
+            .pixels
+            .iter()
+            .map(|pixel| (pixel.pos.x, pixel.pos.y, pixel.fill_char.text_char))
+            .collect();
+        // Only the right side of the first piece touches the other one
+        assert_eq!(borders, HashSet::from([(4, 19, '█'), (5, 19, '▌')]));
+    }
+}
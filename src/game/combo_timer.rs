@@ -0,0 +1,63 @@
+use gemini_engine::core::{CanDraw, Canvas, ColChar, Colour, Vec2D};
+
+/// How many ticks the combo timer lasts after a line clear, 5 seconds at full speed
+const TIMER_LENGTH: u32 = 300;
+/// The board column the timer bar is drawn in, just right of the wall
+const BAR_COLUMN: i64 = 12;
+/// The number of rows the timer bar fills when the timer is full
+const BAR_HEIGHT: u32 = 20;
+
+/// The timer in combo mode, which line clears have to keep refilling to sustain a chain
+#[derive(Debug, Default)]
+pub struct ComboTimer {
+    remaining: u32,
+    /// The number of line clears in a row made before the timer ran out
+    pub chain: u32,
+    pub longest_chain: u32,
+}
+
+impl ComboTimer {
+    /// Advance by one tick
+    ///
+    /// Returns true if the timer has just run out and broken a chain
+    pub const fn tick(&mut self) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            let broke_chain = self.chain > 1;
+            self.chain = 0;
+            return broke_chain;
+        }
+
+        false
+    }
+
+    /// Extend the chain with a line clear and refill the timer
+    pub fn clear_lines(&mut self) {
+        self.chain += 1;
+        self.longest_chain = self.longest_chain.max(self.chain);
+        self.remaining = TIMER_LENGTH;
+    }
+}
+
+impl CanDraw for ComboTimer {
+    /// Draw the timer as a bar beside the board, emptying towards the floor
+    fn draw_to(&self, canvas: &mut impl Canvas) {
+        let filled_rows = (self.remaining * BAR_HEIGHT).div_ceil(TIMER_LENGTH);
+        let colour = if self.remaining * 4 < TIMER_LENGTH {
+            Colour::rgb(255, 0, 0)
+        } else {
+            Colour::rgb(255, 255, 0)
+        };
+
+        for row in 0..filled_rows {
+            canvas.plot(
+                Vec2D::new(BAR_COLUMN, i64::from(BAR_HEIGHT - row) - 1),
+                ColChar::SOLID.with_colour(colour),
+            );
+        }
+    }
+}
@@ -0,0 +1,737 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::Deserialize;
+use std::{collections::BTreeMap, env, fmt, str::FromStr, time::Duration};
+
+/// Keys added in sticky keys mode for actions that are otherwise only bound to key combinations
+const CHORD_FREE_KEYS: [(Action, KeyCode); 1] = [(Action::SonicDrop, KeyCode::Char('v'))];
+
+/// The longest rotation debounce allowed, so deliberate double rotations still register
+const MAX_ROTATION_DEBOUNCE_MS: u64 = 100;
+
+/// The longest DAS cut delay allowed
+const MAX_DAS_CUT_DELAY_MS: u64 = 200;
+
+/// The longest hard drop protection allowed, so deliberate quick drops still register
+const MAX_HARD_DROP_PROTECTION_MS: u64 = 200;
+
+/// The `[input]` section of the config file
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct InputSettings {
+    /// Treat rotations pressed within this many milliseconds of the last one as the same press,
+    /// for keyboards with switches that chatter. Off if 0
+    pub rotation_debounce_ms: u64,
+    /// Rotate only once per press, ignoring the repeats sent while a rotation key is held down
+    pub disable_rotation_repeat: bool,
+    /// Ignore shifts repeated by a held key for this many milliseconds after a rotation or hold,
+    /// so the piece doesn't slide away before the next placement. Off if 0
+    pub das_cut_delay_ms: u64,
+    /// Ignore hard drops pressed within this many milliseconds of the last piece locking, so a
+    /// held or repeated hard drop key doesn't drop the next piece by accident. Off if 0
+    pub hard_drop_protection_ms: u64,
+    /// Start from bindings that can all be reached with the left hand, instead of the default
+    /// ones
+    pub one_handed: bool,
+    /// Never require keys to be held, for players using their system's sticky keys: every action
+    /// gets a binding without modifiers, and soft drop is toggled on and off instead of held
+    pub sticky_keys: bool,
+    /// Hard drop the piece into a column by pressing its number (0 for the tenth) or clicking it
+    pub quick_place: bool,
+}
+
+impl InputSettings {
+    /// Bring any setting that's out of range back to its limit, leaving the rest as they are
+    ///
+    /// Returns the settings with a warning for each one that had to be changed
+    pub fn validate(mut self) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        for (value, max, name) in [
+            (
+                &mut self.rotation_debounce_ms,
+                MAX_ROTATION_DEBOUNCE_MS,
+                "rotation debounce",
+            ),
+            (
+                &mut self.das_cut_delay_ms,
+                MAX_DAS_CUT_DELAY_MS,
+                "DAS cut delay",
+            ),
+            (
+                &mut self.hard_drop_protection_ms,
+                MAX_HARD_DROP_PROTECTION_MS,
+                "hard drop protection",
+            ),
+        ] {
+            if *value > max {
+                warnings.push(format!(
+                    "the {name} can be at most {max}ms, so {max}ms is used instead of {value}ms"
+                ));
+                *value = max;
+            }
+        }
+
+        (self, warnings)
+    }
+
+    pub const fn rotation_debounce(self) -> Duration {
+        Duration::from_millis(self.rotation_debounce_ms)
+    }
+
+    pub const fn das_cut_delay(self) -> Duration {
+        Duration::from_millis(self.das_cut_delay_ms)
+    }
+
+    pub const fn hard_drop_protection(self) -> Duration {
+        Duration::from_millis(self.hard_drop_protection_ms)
+    }
+}
+
+/// An action the player can trigger with a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Pause,
+    ShiftLeft,
+    ShiftRight,
+    RotateAntiClockwise,
+    RotateClockwise,
+    SoftDrop,
+    HardDrop,
+    SonicDrop,
+    Hold,
+    /// Show or hide the rotation centre and kick offsets, in practice mode
+    ToggleRotationOverlay,
+    /// Take control of the other falling piece, in frenzy
+    SwapPiece,
+}
+
+impl Action {
+    const ALL_VARIANTS: [Self; 11] = [
+        Self::Pause,
+        Self::ShiftLeft,
+        Self::ShiftRight,
+        Self::RotateAntiClockwise,
+        Self::RotateClockwise,
+        Self::SoftDrop,
+        Self::HardDrop,
+        Self::SonicDrop,
+        Self::Hold,
+        Self::ToggleRotationOverlay,
+        Self::SwapPiece,
+    ];
+
+    /// The name used for the action in the config file
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Pause => "pause",
+            Self::ShiftLeft => "shift_left",
+            Self::ShiftRight => "shift_right",
+            Self::RotateAntiClockwise => "rotate_anti_clockwise",
+            Self::RotateClockwise => "rotate_clockwise",
+            Self::SoftDrop => "soft_drop",
+            Self::HardDrop => "hard_drop",
+            Self::SonicDrop => "sonic_drop",
+            Self::Hold => "hold",
+            Self::ToggleRotationOverlay => "toggle_rotation_overlay",
+            Self::SwapPiece => "swap_piece",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL_VARIANTS
+            .into_iter()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// The physical keyboard layout, used to keep the default rotation keys in the same place on every keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    Qwerty,
+    Azerty,
+    Qwertz,
+}
+
+impl KeyboardLayout {
+    /// Guess the keyboard layout from the locale environment variables, falling back to QWERTY
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .map_or(Self::Qwerty, |locale| Self::from_locale(&locale))
+    }
+
+    fn from_locale(locale: &str) -> Self {
+        // Strip the encoding and modifier, e.g. `de_DE.UTF-8@euro` becomes `de_DE`
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let (language, region) = locale.split_once('_').unwrap_or((locale, ""));
+
+        match (language, region) {
+            ("fr", "CA") => Self::Qwerty,
+            ("fr" | "it", "CH") | ("de" | "cs" | "sk" | "hu" | "sl" | "hr", _) => Self::Qwertz,
+            ("fr", _) | ("nl", "BE") => Self::Azerty,
+            _ => Self::Qwerty,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "qwerty" => Some(Self::Qwerty),
+            "azerty" => Some(Self::Azerty),
+            "qwertz" => Some(Self::Qwertz),
+            _ => None,
+        }
+    }
+
+    /// The keys sitting where Z and X are on a QWERTY keyboard
+    const fn rotation_keys(self) -> (char, char) {
+        match self {
+            Self::Qwerty => ('z', 'x'),
+            Self::Azerty => ('w', 'x'),
+            Self::Qwertz => ('y', 'x'),
+        }
+    }
+
+    /// The keys at the top left, top middle and home row left of the WASD cluster
+    const fn one_handed_keys(self) -> (char, char, char) {
+        match self {
+            Self::Qwerty | Self::Qwertz => ('q', 'w', 'a'),
+            Self::Azerty => ('a', 'z', 'q'),
+        }
+    }
+}
+
+/// A key together with the modifiers that have to be held alongside it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub const fn new(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub const fn with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Returns true if the pressed key and modifiers trigger this binding. Shift is ignored for
+    /// characters since it only changes their case
+    pub fn matches(&self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match (self.code, code) {
+            (KeyCode::Char(bound), KeyCode::Char(pressed)) => {
+                bound.eq_ignore_ascii_case(&pressed)
+                    && self.modifiers - KeyModifiers::SHIFT == modifiers - KeyModifiers::SHIFT
+            }
+            _ => self.code == code && self.modifiers == modifiers,
+        }
+    }
+
+    /// Why the terminal won't pass this binding on to the game as it's written, if it won't
+    pub fn terminal_conflict(&self) -> Option<&'static str> {
+        let KeyCode::Char(c) = self.code else {
+            return None;
+        };
+        if self.modifiers - KeyModifiers::SHIFT != KeyModifiers::CONTROL {
+            return None;
+        }
+
+        match c.to_ascii_lowercase() {
+            'c' => Some("quits the game"),
+            'z' => Some("suspends the game in most terminals"),
+            '\\' => Some("quits programs in most terminals"),
+            's' | 'q' => Some("freezes or unfreezes output in terminals with flow control"),
+            'm' | 'j' => Some("is sent the same as Enter by terminals"),
+            'i' => Some("is sent the same as Tab by terminals"),
+            'h' => Some("is sent the same as Backspace by many terminals"),
+            '[' => Some("is sent the same as Esc by terminals"),
+            _ => None,
+        }
+    }
+}
+
+/// A binding that won't work the way the config file asks for while playing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingProblem {
+    binding: KeyBinding,
+    action: Action,
+    reason: String,
+}
+
+impl fmt::Display for BindingProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (bound to {}) {}",
+            self.binding,
+            self.action.name(),
+            self.reason
+        )
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl"),
+            (KeyModifiers::ALT, "Alt"),
+            (KeyModifiers::SHIFT, "Shift"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        // Written the way the config file expects them, so fixed bindings can be saved to it
+        match self.code {
+            KeyCode::Char(c) if c != ' ' => write!(f, "{}", c.to_uppercase()),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    /// Parse a binding such as `x`, `Up` or `Ctrl+Space`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{modifier}` in `{s}`")),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "insert" => KeyCode::Insert,
+            "delete" | "del" => KeyCode::Delete,
+            name => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('f'), Some(_)) => name[1..]
+                        .parse()
+                        .map(KeyCode::F)
+                        .map_err(|_| format!("unknown key `{key}` in `{s}`"))?,
+                    _ => return Err(format!("unknown key `{key}` in `{s}`")),
+                }
+            }
+        };
+
+        Ok(Self::with_modifiers(code, modifiers))
+    }
+}
+
+pub struct Controls {
+    bindings: Vec<(KeyBinding, Action)>,
+    /// Actions given their own bindings in the config file
+    customised: Vec<Action>,
+    pub input: InputSettings,
+}
+
+impl Controls {
+    /// Create the default bindings for the given keyboard layout
+    pub fn new(layout: KeyboardLayout) -> Self {
+        let (anti_clockwise_key, clockwise_key) = layout.rotation_keys();
+
+        Self {
+            bindings: vec![
+                (KeyBinding::new(KeyCode::Char('c')), Action::Hold),
+                (KeyBinding::new(KeyCode::Left), Action::ShiftLeft),
+                (KeyBinding::new(KeyCode::Right), Action::ShiftRight),
+                (KeyBinding::new(KeyCode::Char(' ')), Action::HardDrop),
+                (KeyBinding::new(KeyCode::Down), Action::SoftDrop),
+                (
+                    KeyBinding::with_modifiers(KeyCode::Char(' '), KeyModifiers::CONTROL),
+                    Action::SonicDrop,
+                ),
+                (
+                    KeyBinding::new(KeyCode::Char(anti_clockwise_key)),
+                    Action::RotateAntiClockwise,
+                ),
+                (KeyBinding::new(KeyCode::Up), Action::RotateClockwise),
+                (
+                    KeyBinding::new(KeyCode::Char(clockwise_key)),
+                    Action::RotateClockwise,
+                ),
+                (KeyBinding::new(KeyCode::Esc), Action::Pause),
+                (
+                    KeyBinding::new(KeyCode::F(2)),
+                    Action::ToggleRotationOverlay,
+                ),
+                (KeyBinding::new(KeyCode::Tab), Action::SwapPiece),
+            ],
+            customised: Vec::new(),
+            input: InputSettings::default(),
+        }
+    }
+
+    /// Create bindings for playing with only the left hand, around the WASD cluster of the given
+    /// keyboard layout
+    pub fn one_handed(layout: KeyboardLayout) -> Self {
+        let (top_left_key, top_key, left_key) = layout.one_handed_keys();
+
+        Self {
+            bindings: vec![
+                (
+                    KeyBinding::new(KeyCode::Char(top_left_key)),
+                    Action::RotateAntiClockwise,
+                ),
+                (KeyBinding::new(KeyCode::Char(top_key)), Action::HardDrop),
+                (KeyBinding::new(KeyCode::Char('e')), Action::RotateClockwise),
+                (KeyBinding::new(KeyCode::Char(left_key)), Action::ShiftLeft),
+                (KeyBinding::new(KeyCode::Char('s')), Action::SoftDrop),
+                (KeyBinding::new(KeyCode::Char('d')), Action::ShiftRight),
+                (KeyBinding::new(KeyCode::Char('f')), Action::Hold),
+                (KeyBinding::new(KeyCode::Char('x')), Action::SonicDrop),
+                (KeyBinding::new(KeyCode::Esc), Action::Pause),
+                (
+                    KeyBinding::new(KeyCode::F(2)),
+                    Action::ToggleRotationOverlay,
+                ),
+                (KeyBinding::new(KeyCode::Tab), Action::SwapPiece),
+            ],
+            customised: Vec::new(),
+            input: InputSettings::default(),
+        }
+    }
+
+    /// Replace the default bindings of every action listed in `overrides`, keyed by action name
+    ///
+    /// Returns a description of every entry that couldn't be understood
+    pub fn apply_overrides(&mut self, overrides: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for (action_name, keys) in overrides {
+            let Some(action) = Action::from_name(action_name) else {
+                errors.push(format!("unknown action `{action_name}`"));
+                continue;
+            };
+
+            self.bindings
+                .retain(|(_, bound_action)| *bound_action != action);
+            self.customised.push(action);
+            for key in keys {
+                match key.parse() {
+                    Ok(binding) => self.bindings.push((binding, action)),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Find every binding that can't be used while playing: keys the terminal keeps for itself or
+    /// can't tell apart from another key, and keys already bound to another action, which only
+    /// ever trigger the action bound first
+    pub fn find_conflicts(&self) -> Vec<BindingProblem> {
+        let mut problems = Vec::new();
+
+        for (i, (binding, action)) in self.bindings.iter().enumerate() {
+            let reason = binding.terminal_conflict().map(String::from).or_else(|| {
+                self.bindings[..i]
+                    .iter()
+                    .find(|(earlier_binding, earlier_action)| {
+                        earlier_action != action
+                            && earlier_binding.matches(binding.code, binding.modifiers)
+                    })
+                    .map(|(_, earlier_action)| {
+                        format!("is already bound to {}", earlier_action.name())
+                    })
+            });
+
+            if let Some(reason) = reason {
+                problems.push(BindingProblem {
+                    binding: *binding,
+                    action: *action,
+                    reason,
+                });
+            }
+        }
+
+        problems
+    }
+
+    /// Remove the bindings with problems, then give any action left without a key its default
+    /// bindings from `defaults` where they're free. Of two actions sharing a key, the one bound in
+    /// the config file keeps it
+    ///
+    /// Returns the new bindings of every action that changed, keyed by action name, to be saved
+    /// to the config file
+    pub fn fix_conflicts(
+        &mut self,
+        problems: &[BindingProblem],
+        defaults: &Self,
+    ) -> BTreeMap<String, Vec<String>> {
+        let mut changed = Vec::new();
+
+        for problem in problems {
+            let shared_with = self.bindings.iter().position(|(binding, action)| {
+                *action != problem.action
+                    && binding.matches(problem.binding.code, problem.binding.modifiers)
+            });
+            let removed = match shared_with {
+                Some(i)
+                    if problem.binding.terminal_conflict().is_none()
+                        && self.customised.contains(&problem.action)
+                        && !self.customised.contains(&self.bindings[i].1) =>
+                {
+                    self.bindings.remove(i)
+                }
+                _ => {
+                    let Some(i) = self
+                        .bindings
+                        .iter()
+                        .position(|bound| *bound == (problem.binding, problem.action))
+                    else {
+                        continue;
+                    };
+                    self.bindings.remove(i)
+                }
+            };
+            changed.push(removed.1);
+        }
+
+        for &action in &changed {
+            if !self.keys_for(action).is_empty() {
+                continue;
+            }
+            for (binding, _) in defaults
+                .bindings
+                .iter()
+                .filter(|(_, default_action)| *default_action == action)
+            {
+                if self.get_action(binding.code, binding.modifiers).is_none() {
+                    self.bindings.push((*binding, action));
+                }
+            }
+        }
+
+        changed
+            .into_iter()
+            .map(|action| {
+                let keys = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, bound_action)| *bound_action == action)
+                    .map(|(binding, _)| binding.to_string())
+                    .collect();
+                (String::from(action.name()), keys)
+            })
+            .collect()
+    }
+
+    /// Make sure every action can be triggered without holding keys together, by adding a
+    /// single key binding to actions that are only bound to key combinations
+    ///
+    /// Returns a description of every action that still needs a key combination
+    pub fn add_chord_free_bindings(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for action in Action::ALL_VARIANTS {
+            let bindings: Vec<KeyBinding> = self
+                .bindings
+                .iter()
+                .filter(|(_, bound_action)| *bound_action == action)
+                .map(|(binding, _)| *binding)
+                .collect();
+            if bindings.is_empty() || bindings.iter().any(|binding| binding.modifiers.is_empty()) {
+                continue;
+            }
+
+            let fallback = CHORD_FREE_KEYS
+                .iter()
+                .find(|(fallback_action, _)| *fallback_action == action)
+                .map(|(_, code)| KeyBinding::new(*code))
+                .filter(|binding| self.get_action(binding.code, binding.modifiers).is_none());
+            match fallback {
+                Some(binding) => self.bindings.push((binding, action)),
+                None => errors.push(format!(
+                    "{} can only be triggered with a key combination",
+                    action.name()
+                )),
+            }
+        }
+
+        errors
+    }
+
+    /// Get the action bound to the pressed key, if there is one
+    pub fn get_action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.matches(code, modifiers))
+            .map(|(_, action)| *action)
+    }
+
+    /// List every key bound to the action, separated by slashes
+    fn keys_for(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, bound_action)| *bound_action == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    pub fn help_text(&self) -> String {
+        let mut help_text = format!(
+            "Controls:
+{} to hold
+{}/{} to shift
+{} hard | {} soft
+{} AC | {} C rotation
+{} to pause",
+            self.keys_for(Action::Hold),
+            self.keys_for(Action::ShiftLeft),
+            self.keys_for(Action::ShiftRight),
+            self.keys_for(Action::HardDrop),
+            self.keys_for(Action::SoftDrop),
+            self.keys_for(Action::RotateAntiClockwise),
+            self.keys_for(Action::RotateClockwise),
+            self.keys_for(Action::Pause),
+        );
+
+        let sonic_drop_keys = self.keys_for(Action::SonicDrop);
+        if !sonic_drop_keys.is_empty() {
+            help_text.push('\n');
+            help_text.push_str(&sonic_drop_keys);
+            help_text.push_str(" sonic drop");
+        }
+
+        help_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default QWERTY bindings, with the config file's bindings applied over them
+    fn configured(overrides: &[(&str, &[&str])]) -> Controls {
+        let overrides = overrides
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| String::from(*key)).collect();
+                (String::from(*action), keys)
+            })
+            .collect();
+        let mut controls = Controls::new(KeyboardLayout::Qwerty);
+        assert!(controls.apply_overrides(&overrides).is_empty());
+        controls
+    }
+
+    fn fixed(controls: &mut Controls) -> BTreeMap<String, Vec<String>> {
+        let problems = controls.find_conflicts();
+        controls.fix_conflicts(&problems, &Controls::new(KeyboardLayout::Qwerty))
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| String::from(*key)).collect()
+    }
+
+    #[test]
+    fn keys_shared_with_a_default_binding_stay_with_the_customised_action() {
+        let mut controls = configured(&[("hold", &["space"])]);
+        let problems = controls.find_conflicts();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].action, Action::Hold);
+        assert_eq!(problems[0].reason, "is already bound to hard_drop");
+
+        // Hard drop's only default key is taken, so it's left without one
+        assert_eq!(
+            fixed(&mut controls),
+            BTreeMap::from([(String::from("hard_drop"), Vec::new())])
+        );
+        assert_eq!(
+            controls.get_action(KeyCode::Char(' '), KeyModifiers::NONE),
+            Some(Action::Hold)
+        );
+        assert!(controls.find_conflicts().is_empty());
+    }
+
+    #[test]
+    fn keys_shared_between_customised_actions_stay_with_the_first() {
+        let mut controls = configured(&[("hold", &["v"]), ("sonic_drop", &["v"])]);
+        assert_eq!(controls.find_conflicts().len(), 1);
+
+        // Sonic drop gets its default key back
+        assert_eq!(
+            fixed(&mut controls),
+            BTreeMap::from([(String::from("sonic_drop"), keys(&["Ctrl+Space"]))])
+        );
+        assert_eq!(
+            controls.get_action(KeyCode::Char('v'), KeyModifiers::NONE),
+            Some(Action::Hold)
+        );
+    }
+
+    #[test]
+    fn keys_the_terminal_keeps_are_removed() {
+        let mut controls = configured(&[("pause", &["Ctrl+C", "p"])]);
+        let problems = controls.find_conflicts();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].reason, "quits the game");
+
+        assert_eq!(
+            fixed(&mut controls),
+            BTreeMap::from([(String::from("pause"), keys(&["P"]))])
+        );
+    }
+
+    #[test]
+    fn actions_left_without_a_key_get_their_defaults_back() {
+        let mut controls = configured(&[("pause", &["Ctrl+Z"])]);
+        assert_eq!(
+            fixed(&mut controls),
+            BTreeMap::from([(String::from("pause"), keys(&["Esc"]))])
+        );
+    }
+
+    #[test]
+    fn out_of_range_input_settings_only_reset_themselves() {
+        let (input, warnings) = InputSettings {
+            rotation_debounce_ms: 150,
+            hard_drop_protection_ms: 50,
+            sticky_keys: true,
+            quick_place: true,
+            ..InputSettings::default()
+        }
+        .validate();
+
+        assert_eq!(input.rotation_debounce_ms, MAX_ROTATION_DEBOUNCE_MS);
+        assert_eq!(input.hard_drop_protection_ms, 50);
+        assert!(input.sticky_keys && input.quick_place);
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::config::Config;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A finished dig game, kept so players can see their downstacking improve over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigRecord {
+    /// When the game ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The number of garbage rows the board started with
+    pub rows: usize,
+    pub garbage_cleared: usize,
+    pub pieces_placed: usize,
+    /// Whether the game was played with the relaxed preset
+    pub relaxed: bool,
+}
+
+impl DigRecord {
+    pub fn new(rows: usize, garbage_cleared: usize, pieces_placed: usize, relaxed: bool) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            rows,
+            garbage_cleared,
+            pieces_placed,
+            relaxed,
+        }
+    }
+
+    /// Garbage rows cleared per piece placed
+    pub fn efficiency(&self) -> f64 {
+        if self.pieces_placed == 0 {
+            0.0
+        } else {
+            self.garbage_cleared as f64 / self.pieces_placed as f64
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<u64> = line
+            .split(',')
+            .map(|field| field.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        // Games from before the relaxed preset existed have no column for it
+        let (timestamp, rows, garbage_cleared, pieces_placed, relaxed) = match fields[..] {
+            [timestamp, rows, garbage_cleared, pieces_placed] => {
+                (timestamp, rows, garbage_cleared, pieces_placed, 0)
+            }
+            [timestamp, rows, garbage_cleared, pieces_placed, relaxed] => {
+                (timestamp, rows, garbage_cleared, pieces_placed, relaxed)
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            timestamp,
+            rows: rows as usize,
+            garbage_cleared: garbage_cleared as usize,
+            pieces_placed: pieces_placed as usize,
+            relaxed: relaxed == 1,
+        })
+    }
+}
+
+/// The history file, `dig_history.csv` in the config directory
+fn path() -> Option<PathBuf> {
+    Config::directory().map(|dir| dir.join("dig_history.csv"))
+}
+
+/// Read every recorded game, skipping any lines that can't be understood
+pub fn load() -> Vec<DigRecord> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().filter_map(DigRecord::parse).collect())
+        .unwrap_or_default()
+}
+
+/// Add the game to the end of the history file
+pub fn append(record: DigRecord) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::other("no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{},{},{},{},{}",
+        record.timestamp,
+        record.rows,
+        record.garbage_cleared,
+        record.pieces_placed,
+        u8::from(record.relaxed)
+    )
+}
@@ -0,0 +1,139 @@
+use rand::{seq::IteratorRandom, Rng};
+use serde::Deserialize;
+
+/// The columns a garbage hole can be placed in
+const BOARD_COLUMNS: std::ops::RangeInclusive<i64> = 1..=10;
+
+/// The `[dig]` section of the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DigSettings {
+    /// The number of garbage rows the board starts with
+    pub rows: usize,
+    /// The number of holes in every garbage row
+    pub holes_per_row: usize,
+    /// Never place a hole directly above or below another, so every row can be dug out without
+    /// having to clear the rows above it first
+    pub clean: bool,
+}
+
+impl Default for DigSettings {
+    fn default() -> Self {
+        Self {
+            rows: 10,
+            holes_per_row: 1,
+            clean: false,
+        }
+    }
+}
+
+impl DigSettings {
+    /// Check the settings describe a board that can be generated
+    pub fn validate(self) -> Result<Self, String> {
+        // A clean row can't reuse any of the columns in the row above it
+        let max_holes = if self.clean { 5 } else { 9 };
+
+        if !(1..=19).contains(&self.rows) {
+            Err(String::from("dig mode needs between 1 and 19 garbage rows"))
+        } else if !(1..=max_holes).contains(&self.holes_per_row) {
+            Err(format!(
+                "dig mode needs between 1 and {max_holes} holes per row{}",
+                if self.clean { " on a clean board" } else { "" }
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Pick the hole columns for `rows` rows of garbage, each one below the last
+///
+/// `previous_holes` are the holes in the row directly above the first generated row, if any
+pub fn generate_hole_columns(
+    rows: usize,
+    settings: DigSettings,
+    previous_holes: &[i64],
+    rng: &mut impl Rng,
+) -> Vec<Vec<i64>> {
+    let mut hole_rows: Vec<Vec<i64>> = Vec::with_capacity(rows);
+
+    for _ in 0..rows {
+        let row_above = hole_rows.last().map_or(previous_holes, Vec::as_slice);
+
+        let mut holes = BOARD_COLUMNS
+            .filter(|x| !settings.clean || !row_above.contains(x))
+            .choose_multiple(rng, settings.holes_per_row);
+        holes.sort_unstable();
+
+        hole_rows.push(holes);
+    }
+
+    hole_rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn settings(holes_per_row: usize, clean: bool) -> DigSettings {
+        DigSettings {
+            rows: 19,
+            holes_per_row,
+            clean,
+        }
+    }
+
+    #[test]
+    fn rows_have_the_requested_number_of_distinct_holes() {
+        for holes_per_row in 1..=9 {
+            let mut rng = StdRng::seed_from_u64(holes_per_row as u64);
+            let hole_rows =
+                generate_hole_columns(19, settings(holes_per_row, false), &[], &mut rng);
+
+            assert_eq!(hole_rows.len(), 19);
+            for holes in hole_rows {
+                assert_eq!(holes.len(), holes_per_row);
+                assert!(holes.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(holes.iter().all(|x| BOARD_COLUMNS.contains(x)));
+            }
+        }
+    }
+
+    #[test]
+    fn clean_rows_never_share_a_hole_with_the_row_above() {
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let holes_per_row = (seed % 5 + 1) as usize;
+            let previous_holes = [1, 2, 3, 4, 5];
+
+            let hole_rows = generate_hole_columns(
+                19,
+                settings(holes_per_row, true),
+                &previous_holes[..holes_per_row],
+                &mut rng,
+            );
+
+            assert!(hole_rows[0]
+                .iter()
+                .all(|x| !previous_holes[..holes_per_row].contains(x)));
+            for pair in hole_rows.windows(2) {
+                assert!(pair[1].iter().all(|x| !pair[0].contains(x)));
+            }
+        }
+    }
+
+    #[test]
+    fn validation_rejects_impossible_boards() {
+        assert!(settings(5, true).validate().is_ok());
+        assert!(settings(6, true).validate().is_err());
+        assert!(settings(9, false).validate().is_ok());
+        assert!(settings(0, false).validate().is_err());
+        assert!(DigSettings {
+            rows: 20,
+            ..DigSettings::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
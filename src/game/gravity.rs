@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// The fastest gravity allowed, which drops a block straight to the floor in a single frame
+const MAX_CELLS_PER_FRAME: f32 = 20.0;
+/// The fastest gravity in the relaxed preset, one cell every half a second
+const RELAXED_MAX_CELLS_PER_FRAME: f32 = 1.0 / 30.0;
+/// The shortest lock delay in the relaxed preset, in frames
+const RELAXED_MIN_LOCK_DELAY: u32 = 90;
+
+/// One row of a gravity curve, taking effect from `level` until the next row's level
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GravityStep {
+    pub level: u32,
+    /// How many cells the active block falls every frame. Fractions carry over between frames,
+    /// e.g. `0.5` falls one cell every other frame
+    pub cells_per_frame: f32,
+    /// Frames a block can rest on the ground before it locks in place
+    pub lock_delay: u32,
+}
+
+/// The gravity and lock delay used at every level, read from the `[[gravity]]` rows of the config
+/// file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<GravityStep>", into = "Vec<GravityStep>")]
+pub struct GravityCurve {
+    steps: Vec<GravityStep>,
+}
+
+impl Default for GravityCurve {
+    fn default() -> Self {
+        Self {
+            steps: vec![GravityStep {
+                level: 1,
+                cells_per_frame: 1.0 / 12.0,
+                lock_delay: 30,
+            }],
+        }
+    }
+}
+
+impl GravityCurve {
+    /// Build a curve from the config rows
+    pub fn new(steps: Vec<GravityStep>) -> Result<Self, String> {
+        match steps.first() {
+            None => return Err(String::from("the gravity curve needs at least one row")),
+            Some(first) if first.level != 1 => {
+                return Err(String::from("the gravity curve must start at level 1"));
+            }
+            Some(_) => (),
+        }
+        for pair in steps.windows(2) {
+            if pair[1].level <= pair[0].level {
+                return Err(format!(
+                    "gravity curve levels must be in increasing order, but level {} follows level {}",
+                    pair[1].level, pair[0].level
+                ));
+            }
+        }
+        for step in &steps {
+            if !(step.cells_per_frame > 0.0 && step.cells_per_frame <= MAX_CELLS_PER_FRAME) {
+                return Err(format!(
+                    "gravity at level {} must be above 0 and at most {MAX_CELLS_PER_FRAME} cells per frame",
+                    step.level
+                ));
+            }
+            if step.lock_delay == 0 {
+                return Err(format!(
+                    "lock delay at level {} must be at least 1 frame",
+                    step.level
+                ));
+            }
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// The NES level speeds, where level 1 here is the NES's level 0. Blocks lock as soon as they
+    /// land
+    pub fn classic() -> Self {
+        // The frames it takes to fall one cell, from each level until the next
+        const FRAMES_PER_CELL: [(u32, u32); 15] = [
+            (1, 48),
+            (2, 43),
+            (3, 38),
+            (4, 33),
+            (5, 28),
+            (6, 23),
+            (7, 18),
+            (8, 13),
+            (9, 8),
+            (10, 6),
+            (11, 5),
+            (14, 4),
+            (17, 3),
+            (20, 2),
+            (30, 1),
+        ];
+
+        Self {
+            steps: FRAMES_PER_CELL
+                .into_iter()
+                .map(|(level, frames)| GravityStep {
+                    level,
+                    cells_per_frame: 1.0 / frames as f32,
+                    lock_delay: 1,
+                })
+                .collect(),
+        }
+    }
+
+    /// The same curve with gravity capped and lock delay extended, for the relaxed preset
+    pub fn relaxed(&self) -> Self {
+        Self {
+            steps: self
+                .steps
+                .iter()
+                .map(|step| GravityStep {
+                    level: step.level,
+                    cells_per_frame: step.cells_per_frame.min(RELAXED_MAX_CELLS_PER_FRAME),
+                    lock_delay: step.lock_delay.max(RELAXED_MIN_LOCK_DELAY),
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the row in effect at the given level
+    pub fn step_for(&self, level: u32) -> GravityStep {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| step.level <= level)
+            .copied()
+            .unwrap_or(self.steps[0])
+    }
+}
+
+impl TryFrom<Vec<GravityStep>> for GravityCurve {
+    type Error = String;
+
+    fn try_from(steps: Vec<GravityStep>) -> Result<Self, Self::Error> {
+        Self::new(steps)
+    }
+}
+
+impl From<GravityCurve> for Vec<GravityStep> {
+    fn from(curve: GravityCurve) -> Self {
+        curve.steps
+    }
+}
@@ -0,0 +1,245 @@
+//! Tests holding the core to the Tetris Guideline, so the behaviour it shares with modern games
+//! doesn't drift as the code changes
+//!
+//! Covered: spawn positions and orientations, the bag randomizer, the SRS kick tables and their
+//! documented kicks, and the base line clear and T-spin scores. The modern ruleset still differs
+//! from the guideline in ways these tests don't claim: scores aren't multiplied by the level, there
+//! is no back-to-back bonus, hard drops score 1 point per cell instead of 2, only the stack (not
+//! the walls or floor) counts towards a T-spin's corners, and the first bag of a game is a partial
+//! one
+
+use super::{
+    alerts::generate_alert_for_filled_lines,
+    block_manager::{
+        rotation_system::{RotationSystem, Srs},
+        tetris_core, Block, BlockManager, BlockType,
+    },
+    collision_manager::CollisionManager,
+    messages::Message,
+    rules::Randomizer,
+    PieceColours,
+};
+use gemini_engine::{
+    containers::PixelContainer,
+    core::{ColChar, Vec2D},
+    primitives::Pixel,
+};
+use std::collections::HashSet;
+
+/// The offsets tried for each rotation between a `(from, to)` pair of states
+type KickTable = [((usize, usize), [(i64, i64); 5]); 8];
+
+/// The cells covered by the block
+fn cells(block: &Block) -> HashSet<(i64, i64)> {
+    let mut container = PixelContainer::new();
+    container.draw(block);
+    container
+        .pixels
+        .iter()
+        .map(|pixel| (pixel.pos.x, pixel.pos.y))
+        .collect()
+}
+
+/// A board with the cells filled in. The board's columns are 1 to 10 and its rows 0 to 19
+fn board(filled: &[(i64, i64)]) -> CollisionManager {
+    let mut collision_manager = CollisionManager::new();
+    for &(x, y) in filled {
+        collision_manager.draw(&Pixel::new(Vec2D::new(x, y), ColChar::SOLID));
+    }
+    collision_manager
+}
+
+/// The bottom rows of the board filled in, apart from the cells left empty
+fn rows_except(rows: &[i64], empty: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    rows.iter()
+        .flat_map(|&y| (1..=10).map(move |x| (x, y)))
+        .filter(|cell| !empty.contains(cell))
+        .collect()
+}
+
+#[test]
+fn pieces_spawn_flat_side_down_in_the_middle_columns() {
+    // 3 wide pieces spawn in columns 4 to 6, and the I and O pieces in the middle columns
+    let expected: [(BlockType, &[(i64, i64)]); 7] = [
+        (BlockType::I, &[(4, 0), (5, 0), (6, 0), (7, 0)]),
+        (BlockType::O, &[(5, -1), (6, -1), (5, 0), (6, 0)]),
+        (BlockType::T, &[(5, -1), (4, 0), (5, 0), (6, 0)]),
+        (BlockType::J, &[(4, -1), (4, 0), (5, 0), (6, 0)]),
+        (BlockType::L, &[(6, -1), (4, 0), (5, 0), (6, 0)]),
+        (BlockType::S, &[(5, -1), (6, -1), (4, 0), (5, 0)]),
+        (BlockType::Z, &[(4, -1), (5, -1), (5, 0), (6, 0)]),
+    ];
+
+    for (shape, expected) in expected {
+        assert_eq!(
+            cells(&Block::new(shape)),
+            expected.iter().copied().collect(),
+            "{shape:?} spawned in the wrong place"
+        );
+    }
+}
+
+#[test]
+fn bag_randomizer_deals_every_piece_once_per_bag() {
+    let mut block_manager = BlockManager::new(
+        30,
+        3,
+        1,
+        Randomizer::Bag,
+        &Srs,
+        PieceColours::default(),
+        Some(1),
+    );
+    let mut pieces = vec![block_manager.block.shape];
+    pieces.extend(block_manager.upcoming_pieces(69));
+
+    // The first bag is cut short, so look for where the full bags start
+    let deals_bags = |start: usize| {
+        pieces[..start].iter().collect::<HashSet<_>>().len() == start
+            && pieces[start..]
+                .chunks_exact(7)
+                .all(|bag| bag.iter().collect::<HashSet<_>>().len() == 7)
+    };
+    assert!(
+        (1..=7).any(deals_bags),
+        "The pieces weren't dealt in bags: {pieces:?}"
+    );
+}
+
+#[test]
+fn srs_kick_tables_match_the_guideline() {
+    // The guideline's tables, with y pointing up as they're usually written
+    const JLSTZ: KickTable = [
+        ((0, 1), [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+        ((1, 0), [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+        ((1, 2), [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+        ((2, 1), [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+        ((2, 3), [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+        ((3, 2), [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+        ((3, 0), [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+        ((0, 3), [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+    ];
+    const I: KickTable = [
+        ((0, 1), [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]),
+        ((1, 0), [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]),
+        ((1, 2), [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]),
+        ((2, 1), [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]),
+        ((2, 3), [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]),
+        ((3, 2), [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]),
+        ((3, 0), [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]),
+        ((0, 3), [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]),
+    ];
+
+    for shape in BlockType::ALL_VARIANTS {
+        let table = match shape {
+            BlockType::O => continue,
+            BlockType::I => I,
+            _ => JLSTZ,
+        };
+        for (rotation, kicks) in table {
+            // The board's y points down
+            let expected: Vec<Vec2D> = kicks.iter().map(|&(x, y)| Vec2D::new(x, -y)).collect();
+            assert_eq!(
+                Srs.kicks(shape, rotation, &[]),
+                expected,
+                "{shape:?} kicks wrongly from state {} to {}",
+                rotation.0,
+                rotation.1
+            );
+        }
+    }
+
+    assert!(Srs.kicks(BlockType::O, (0, 1), &[]).is_empty());
+}
+
+#[test]
+fn vertical_i_kicks_off_the_left_wall() {
+    let collision_manager = board(&[]);
+    let mut block = Block::new(BlockType::I);
+    block.rotation = 1;
+    block.pos = Vec2D::new(0, 10);
+    assert_eq!(
+        cells(&block),
+        HashSet::from([(1, 9), (1, 10), (1, 11), (1, 12)])
+    );
+
+    // The first two offsets leave it in the wall, so it's kicked 2 to the right
+    let attempt = tetris_core::try_rotate_block(&collision_manager.get(), &mut block, true);
+    assert_eq!(attempt.accepted, Some(2));
+    assert_eq!(
+        cells(&block),
+        HashSet::from([(1, 11), (2, 11), (3, 11), (4, 11)])
+    );
+}
+
+#[test]
+fn t_spin_triple_uses_the_fifth_kick() {
+    // A T-spin triple slot in column 3, covered by an overhang
+    let mut filled = rows_except(&[17, 18, 19], &[(3, 17), (2, 18), (3, 18), (3, 19)]);
+    filled.extend([(3, 15), (4, 15), (4, 16)]);
+    let mut collision_manager = board(&filled);
+
+    let mut block = Block::new(BlockType::T);
+    block.pos = Vec2D::new(2, 16);
+    let attempt = tetris_core::try_rotate_block(&collision_manager.get(), &mut block, false);
+
+    assert_eq!(attempt.accepted, Some(4));
+    assert_eq!(block.rotation, 3);
+    assert_eq!(
+        cells(&block),
+        HashSet::from([(3, 17), (2, 18), (3, 18), (3, 19)])
+    );
+
+    let stack = collision_manager.stationary_blocks.clone();
+    let mut piece = PixelContainer::new();
+    piece.draw(&block);
+    let cleared_lines = collision_manager.draw_and_clear_lines(&piece);
+    assert_eq!(cleared_lines, 3);
+    assert_eq!(
+        tetris_core::handle_t_spin(&stack, &block, cleared_lines),
+        Some((1600, Message::TSpin { lines: 3 }))
+    );
+}
+
+#[test]
+fn t_spin_double_scores_1200() {
+    // A T pointing down into a slot in column 4, with an overhang to its top left
+    let mut filled = rows_except(&[18, 19], &[(3, 18), (4, 18), (5, 18), (4, 19)]);
+    filled.push((3, 17));
+    let stack = board(&filled).stationary_blocks;
+
+    let mut block = Block::new(BlockType::T);
+    block.rotation = 2;
+    block.pos = Vec2D::new(4, 18);
+
+    assert_eq!(
+        tetris_core::handle_t_spin(&stack, &block, 2),
+        Some((1200, Message::TSpin { lines: 2 }))
+    );
+}
+
+#[test]
+fn t_spin_scores_match_the_guideline() {
+    // A T wedged into a slot with every corner around it filled
+    let stack = board(&[(4, 18), (6, 18), (4, 20), (6, 20)]).stationary_blocks;
+    let mut block = Block::new(BlockType::T);
+    block.pos = Vec2D::new(5, 19);
+
+    for (lines, score) in [(0, 400), (1, 800), (2, 1200), (3, 1600)] {
+        assert_eq!(
+            tetris_core::handle_t_spin(&stack, &block, lines),
+            Some((score, Message::TSpin { lines }))
+        );
+    }
+}
+
+#[test]
+fn line_clear_scores_match_the_guideline() {
+    for (lines, score) in [(1, 100), (2, 300), (3, 500), (4, 800)] {
+        assert_eq!(
+            generate_alert_for_filled_lines(lines),
+            Some((score, Message::LineClear { lines }))
+        );
+    }
+    assert_eq!(generate_alert_for_filled_lines(0), None);
+}
@@ -0,0 +1,135 @@
+use super::{Action, InputSettings};
+use crossterm::event::KeyEventKind;
+use std::time::{Duration, Instant};
+
+/// Presses of the same key closer together than this are taken to be the terminal repeating a
+/// held key. Only a fallback for terminals that don't mark repeats (including every terminal on
+/// Windows), since it can't be exact: the first repeat only comes after the system's repeat delay,
+/// so it gets through, and deliberate double taps quicker than this are taken for repeats
+const REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Drops key presses that shouldn't reach the game: chattering rotations, rotations repeated by
+/// holding the key down, auto-shift straight after a rotation or hold, and hard drops straight
+/// after a piece locks
+#[derive(Debug, Default)]
+pub struct InputFilter {
+    /// Whether the terminal marks repeated presses, so they don't have to be guessed from timing
+    pub repeats_reported: bool,
+    /// The last rotation pressed and when, to debounce chattering keys and spot repeats
+    rotation: Option<(Action, Instant)>,
+    /// The last shift pressed and when, to spot repeats
+    shift: Option<(Action, Instant)>,
+    /// When the last rotation or hold went through, to cut auto-shift for a moment after it
+    cut_start: Option<Instant>,
+    /// When the last piece locked, to protect the next one from a hard drop meant for it
+    locked_at: Option<Instant>,
+}
+
+impl InputFilter {
+    /// Returns true if the pressed action should be ignored
+    pub fn ignore(&mut self, action: Action, kind: KeyEventKind, settings: InputSettings) -> bool {
+        let now = Instant::now();
+
+        match action {
+            Action::RotateAntiClockwise | Action::RotateClockwise => {
+                let last_rotation = self.rotation.replace((action, now));
+                // Chatter is a single key bouncing, so only the same rotation is debounced
+                let is_chatter = last_rotation.is_some_and(|(last_action, last)| {
+                    last_action == action && now - last < settings.rotation_debounce()
+                });
+                let is_repeat =
+                    settings.disable_rotation_repeat && self.is_repeat(action, kind, last_rotation);
+
+                let ignored = is_chatter || is_repeat;
+                if !ignored {
+                    self.cut_start = Some(now);
+                }
+                ignored
+            }
+            Action::ShiftLeft | Action::ShiftRight => {
+                let last_shift = self.shift.replace((action, now));
+
+                self.is_repeat(action, kind, last_shift)
+                    && self
+                        .cut_start
+                        .is_some_and(|cut_start| now - cut_start < settings.das_cut_delay())
+            }
+            Action::Hold => {
+                self.cut_start = Some(now);
+                false
+            }
+            Action::HardDrop => self
+                .locked_at
+                .is_some_and(|locked_at| now - locked_at < settings.hard_drop_protection()),
+            _ => false,
+        }
+    }
+
+    /// Whether the press is the terminal repeating a held key, going by the event's kind if the
+    /// terminal reports it, or else by how soon it came after the last press of the key
+    fn is_repeat(
+        &self,
+        action: Action,
+        kind: KeyEventKind,
+        last: Option<(Action, Instant)>,
+    ) -> bool {
+        if self.repeats_reported {
+            return kind == KeyEventKind::Repeat;
+        }
+
+        last.is_some_and(|(last_action, last)| {
+            last_action == action && last.elapsed() < REPEAT_INTERVAL
+        })
+    }
+
+    /// Call when a piece locks, to start the hard drop protection window
+    pub fn piece_locked(&mut self) {
+        self.locked_at = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debounced() -> InputSettings {
+        InputSettings {
+            rotation_debounce_ms: 100,
+            ..InputSettings::default()
+        }
+    }
+
+    #[test]
+    fn chattering_rotation_is_debounced() {
+        let mut filter = InputFilter::default();
+        assert!(!filter.ignore(Action::RotateClockwise, KeyEventKind::Press, debounced()));
+        assert!(filter.ignore(Action::RotateClockwise, KeyEventKind::Press, debounced()));
+    }
+
+    #[test]
+    fn reported_repeats_are_told_apart_from_double_taps() {
+        let settings = InputSettings {
+            disable_rotation_repeat: true,
+            ..InputSettings::default()
+        };
+        let mut filter = InputFilter {
+            repeats_reported: true,
+            ..InputFilter::default()
+        };
+        assert!(!filter.ignore(Action::RotateClockwise, KeyEventKind::Press, settings));
+        assert!(filter.ignore(Action::RotateClockwise, KeyEventKind::Repeat, settings));
+        // A quick second press is a deliberate double tap, not a repeat
+        assert!(!filter.ignore(Action::RotateClockwise, KeyEventKind::Press, settings));
+    }
+
+    #[test]
+    fn opposite_rotations_are_not_debounced() {
+        let mut filter = InputFilter::default();
+        assert!(!filter.ignore(Action::RotateClockwise, KeyEventKind::Press, debounced()));
+        assert!(!filter.ignore(
+            Action::RotateAntiClockwise,
+            KeyEventKind::Press,
+            debounced()
+        ));
+    }
+}
@@ -0,0 +1,191 @@
+use super::{Action, Ruleset};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+/// An input applied to the game, along with the frame it was applied on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LoggedInput {
+    frame: u64,
+    action: Action,
+    /// The column a quick placed piece was dropped into
+    column: Option<i64>,
+}
+
+impl LoggedInput {
+    /// Read an input from a line of the log, written as the frame followed by the action, or by
+    /// `quick_place` and the column
+    fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (frame, action, column) = match words[..] {
+            [frame, "quick_place", column] => (
+                frame,
+                Action::HardDrop,
+                Some(
+                    column
+                        .parse()
+                        .map_err(|_| format!("{column} isn't a column"))?,
+                ),
+            ),
+            [frame, action] => (
+                frame,
+                Action::from_name(action).ok_or_else(|| format!("{action} isn't an action"))?,
+                None,
+            ),
+            _ => return Err(String::from("expected a frame and an action")),
+        };
+
+        Ok(Self {
+            frame: frame
+                .parse()
+                .map_err(|_| format!("{frame} isn't a frame number"))?,
+            action,
+            column,
+        })
+    }
+}
+
+/// Writes every input of the game to a text file as it's applied, so the game can be replayed.
+/// The file can be edited by hand to craft a run, e.g. to reproduce a bug
+pub struct InputRecorder {
+    file: File,
+}
+
+impl InputRecorder {
+    /// Start a new log for a game played with the seed, speed and rules. Whether soft drop is
+    /// toggled with sticky keys is logged too, since the same soft drop inputs play out
+    /// differently with it on
+    pub fn create(
+        path: &Path,
+        seed: u64,
+        game_speed: f32,
+        sticky_keys: bool,
+        rules: &Ruleset,
+    ) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# console-tetris input log")?;
+        writeln!(
+            file,
+            "# Replay it with --replay, which uses the options below. Each line after them is the"
+        )?;
+        writeln!(
+            file,
+            "# frame an input was applied on (60 to a second) and its action, or quick_place and"
+        )?;
+        writeln!(file, "# the column")?;
+        writeln!(file, "seed {seed}")?;
+        writeln!(file, "speed {game_speed}")?;
+        writeln!(file, "sticky_keys {sticky_keys}")?;
+        let rules = toml::to_string(rules).expect("Every rule can be written as TOML");
+        for line in rules.lines().filter(|line| !line.is_empty()) {
+            writeln!(file, "rules {line}")?;
+        }
+
+        Ok(Self { file })
+    }
+
+    /// Add an input to the log. Written straight away, so the log survives quitting with Ctrl+C
+    pub fn record(&mut self, frame: u64, action: Action, column: Option<i64>) {
+        let _ = match column {
+            Some(column) => writeln!(self.file, "{frame} quick_place {column}"),
+            None => writeln!(self.file, "{frame} {}", action.name()),
+        };
+    }
+}
+
+/// The inputs of a recorded game, applied again on the same frames. The replay has to be played
+/// with the recorded game's options to play out the same way
+pub struct Replay {
+    pub seed: u64,
+    pub speed: f32,
+    pub sticky_keys: bool,
+    pub rules: Ruleset,
+    inputs: VecDeque<LoggedInput>,
+}
+
+impl Replay {
+    /// Read an input log
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+
+        let mut seed = None;
+        let mut game_speed = None;
+        let mut sticky_keys = None;
+        let mut rules = String::new();
+        let mut inputs = VecDeque::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |error| format!("{} line {}: {error}", path.display(), i + 1);
+            if let Some(value) = line.strip_prefix("seed ") {
+                seed = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| error(format!("{value} isn't a seed")))?,
+                );
+            } else if let Some(value) = line.strip_prefix("speed ") {
+                game_speed = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| error(format!("{value} isn't a speed")))?,
+                );
+            } else if let Some(value) = line.strip_prefix("sticky_keys ") {
+                sticky_keys = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| error(format!("{value} isn't true or false")))?,
+                );
+            } else if let Some(value) = line.strip_prefix("rules ") {
+                rules.push_str(value);
+                rules.push('\n');
+            } else {
+                inputs.push_back(LoggedInput::parse(line).map_err(error)?);
+            }
+        }
+
+        let missing = |option| format!("{} has no {option}", path.display());
+        let seed = seed.ok_or_else(|| missing("seed"))?;
+        let game_speed = game_speed.ok_or_else(|| missing("speed"))?;
+        let sticky_keys = sticky_keys.ok_or_else(|| missing("sticky_keys"))?;
+        if rules.is_empty() {
+            return Err(missing("rules"));
+        }
+        let rules = toml::from_str(&rules)
+            .map_err(|error| format!("{} has invalid rules: {error}", path.display()))?;
+        // Lines edited by hand may be out of order
+        inputs.make_contiguous().sort_by_key(|input| input.frame);
+
+        Ok(Self {
+            seed,
+            speed: game_speed,
+            sticky_keys,
+            rules,
+            inputs,
+        })
+    }
+
+    /// The next input due on or before the frame, if there is one
+    pub fn next(&mut self, frame: u64) -> Option<(Action, Option<i64>)> {
+        if self.inputs.front()?.frame > frame {
+            return None;
+        }
+        self.inputs
+            .pop_front()
+            .map(|input| (input.action, input.column))
+    }
+
+    /// Whether every input has been applied
+    pub fn is_finished(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
@@ -0,0 +1,170 @@
+use gemini_engine::core::{CanDraw, Canvas, ColChar, Vec2D};
+use std::env;
+
+/// Where the board is drawn in the broadcast layout, before it's scaled up to double size
+pub const BROADCAST_BOARD_OFFSET: Vec2D = Vec2D::new(15, 3);
+
+/// How the game is arranged on screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// The board with a sidebar for the score, previews and controls
+    Standard,
+    /// The board with a narrow sidebar showing a single next piece and abbreviated stats, for
+    /// small terminals
+    Compact,
+    /// The standard layout with the score, level and timer written out in large digits in an
+    /// extra sidebar, for low-vision players
+    LargePrint,
+    /// An enlarged board with big readouts and no menus, meant for projecting or streaming
+    Broadcast { player_name: String },
+}
+
+impl Layout {
+    /// Create the broadcast layout, falling back to the user's login name if no name is given
+    pub fn broadcast(player_name: Option<String>) -> Self {
+        let player_name = player_name
+            .or_else(|| env::var("USER").ok())
+            .or_else(|| env::var("USERNAME").ok())
+            .unwrap_or_else(|| String::from("Player"));
+
+        Self::Broadcast { player_name }
+    }
+
+    /// The width and height of the view needed to fit the layout
+    pub const fn view_size(&self) -> (usize, usize) {
+        match self {
+            Self::Standard => (52, 21),
+            Self::Compact => (40, 21),
+            Self::LargePrint => (86, 21),
+            Self::Broadcast { .. } => (110, 46),
+        }
+    }
+
+    /// The name of the layout, as used for its command line option
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Compact => "compact",
+            Self::LargePrint => "large print",
+            Self::Broadcast { .. } => "broadcast",
+        }
+    }
+
+    /// Switch to the standard layout if the terminal is too small for this one, or to the compact
+    /// layout if it's too small for the standard one as well
+    ///
+    /// Returns the layout to use, with a warning if it had to be switched
+    pub fn fit_to(self, columns: u16, rows: u16) -> (Self, Option<String>) {
+        let fits = |layout: &Self| {
+            let (width, height) = layout.view_size();
+            width <= columns as usize && height <= rows as usize
+        };
+        if self == Self::Compact || fits(&self) {
+            return (self, None);
+        }
+
+        let fallback = if fits(&Self::Standard) {
+            Self::Standard
+        } else {
+            Self::Compact
+        };
+        let (width, height) = self.view_size();
+        let warning = format!(
+            "The terminal is {columns}x{rows}, too small for the {} layout ({width}x{height}), so the {} layout is used",
+            self.name(),
+            fallback.name()
+        );
+        (fallback, Some(warning))
+    }
+
+    /// Where alerts are centered, in the middle of the board
+    pub const fn alert_position(&self) -> Vec2D {
+        match self {
+            Self::Standard | Self::Compact | Self::LargePrint => Vec2D::new(12, 7),
+            Self::Broadcast { .. } => Vec2D::new(54, 17),
+        }
+    }
+
+    /// The column of the board under a column of the terminal, if it's over the board
+    pub const fn board_column(&self, terminal_column: u16) -> Option<i64> {
+        // Every cell is drawn two characters wide
+        let cell = terminal_column as i64 / 2;
+        let column = match self {
+            Self::Standard | Self::Compact | Self::LargePrint => cell,
+            Self::Broadcast { .. } => (cell - BROADCAST_BOARD_OFFSET.x).div_euclid(2),
+        };
+
+        if column >= 1 && column <= 10 {
+            Some(column)
+        } else {
+            None
+        }
+    }
+}
+
+/// Draws an element scaled up and offset, e.g. to enlarge the board
+pub struct Transformed<'a, E: CanDraw> {
+    element: &'a E,
+    scale: i64,
+    offset: Vec2D,
+}
+
+impl<'a, E: CanDraw> Transformed<'a, E> {
+    pub const fn new(element: &'a E, scale: i64, offset: Vec2D) -> Self {
+        Self {
+            element,
+            scale,
+            offset,
+        }
+    }
+}
+
+impl<E: CanDraw> CanDraw for Transformed<'_, E> {
+    fn draw_to(&self, canvas: &mut impl Canvas) {
+        self.element.draw_to(&mut TransformedCanvas {
+            canvas,
+            scale: self.scale,
+            offset: self.offset,
+        });
+    }
+}
+
+struct TransformedCanvas<'a, C: Canvas> {
+    canvas: &'a mut C,
+    scale: i64,
+    offset: Vec2D,
+}
+
+impl<C: Canvas> Canvas for TransformedCanvas<'_, C> {
+    fn plot(&mut self, pos: Vec2D, c: ColChar) {
+        let corner = pos * self.scale + self.offset;
+        for y in 0..self.scale {
+            for x in 0..self.scale {
+                self.canvas.plot(corner + Vec2D::new(x, y), c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_terminals_fall_back_to_standard_before_compact() {
+        // Too narrow for large print, but wide enough for the standard layout
+        let (layout, warning) = Layout::LargePrint.fit_to(80, 24);
+        assert_eq!(layout, Layout::Standard);
+        assert!(warning.is_some());
+
+        let (layout, warning) = Layout::broadcast(Some(String::from("Player"))).fit_to(80, 24);
+        assert_eq!(layout, Layout::Standard);
+        assert!(warning.is_some());
+
+        // Too narrow for the standard layout as well
+        assert_eq!(Layout::LargePrint.fit_to(45, 24).0, Layout::Compact);
+        assert_eq!(Layout::Standard.fit_to(45, 24).0, Layout::Compact);
+
+        assert_eq!(Layout::Standard.fit_to(80, 24), (Layout::Standard, None));
+    }
+}
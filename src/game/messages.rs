@@ -0,0 +1,79 @@
+/// Something to tell the player, kept apart from its wording so each language can phrase, order
+/// and pluralise it in its own way rather than having pieces of English glued together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    LineClear { lines: i64 },
+    TSpin { lines: i64 },
+    SlowMotion,
+    SlowMotionTriggers { count: usize },
+    Combo { chain: u32 },
+    ComboBroken,
+    Cascade { chain: i64 },
+}
+
+impl Message {
+    /// Tetrises and T-spins, which are shown even when only important alerts are
+    pub const fn is_important(self) -> bool {
+        matches!(self, Self::LineClear { lines: 4 } | Self::TSpin { .. })
+    }
+}
+
+/// The plural forms a language can use for a count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    One,
+    Other,
+}
+
+/// The languages messages can be written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+}
+
+impl Language {
+    /// Which plural form the language uses for the count
+    pub const fn plural_category(self, count: u64) -> PluralCategory {
+        match self {
+            Self::English if count == 1 => PluralCategory::One,
+            Self::English => PluralCategory::Other,
+        }
+    }
+
+    /// Write the message out in this language
+    pub fn format(self, message: Message) -> String {
+        match self {
+            Self::English => self.format_english(message),
+        }
+    }
+
+    fn format_english(self, message: Message) -> String {
+        const CLEAR_NAMES: [&str; 4] = ["Single", "Double", "Triple", "Tetris"];
+
+        match message {
+            Message::LineClear {
+                lines: lines @ 1..=4,
+            } => format!("{}!", CLEAR_NAMES[(lines - 1) as usize]),
+            Message::LineClear { lines } => match self.plural_category(lines as u64) {
+                PluralCategory::One => format!("{lines} Line!"),
+                PluralCategory::Other => format!("{lines} Lines!"),
+            },
+            Message::TSpin { lines: 0 } => String::from("T-Spin!"),
+            Message::TSpin {
+                lines: lines @ 1..=3,
+            } => {
+                format!("T-Spin {}!", CLEAR_NAMES[(lines - 1) as usize])
+            }
+            Message::TSpin { .. } => String::from("T-Spin?"),
+            Message::SlowMotion => String::from("Slow motion!"),
+            Message::Combo { chain } => format!("{chain} Combo!"),
+            Message::ComboBroken => String::from("Combo broken"),
+            Message::Cascade { chain } => format!("{chain}x Cascade!"),
+            Message::SlowMotionTriggers { count } => match self.plural_category(count as u64) {
+                PluralCategory::One => format!("Slow motion kicked in {count} time"),
+                PluralCategory::Other => format!("Slow motion kicked in {count} times"),
+            },
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use super::garbage::DigSettings;
+
+/// The slowest and fastest speeds a practice game can be played at
+const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+
+/// The rules the game is being played under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    /// The standard scored game
+    Marathon,
+    /// An unranked game, which can be slowed down or sped up to practice at a comfortable pace.
+    /// Seeding it deals the same piece sequence every time, which can be revealed on the pause
+    /// screen to plan out a run
+    Practice {
+        speed: f32,
+        seed: Option<u64>,
+        reveal_sequence: bool,
+    },
+    /// Dig through rows of garbage to clear the board
+    Dig(DigSettings),
+    /// Keep a combo timer running by clearing lines before it runs out, to build the longest
+    /// chain of clears
+    Combo,
+}
+
+impl GameMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "marathon" => Some(Self::Marathon),
+            "practice" => Some(Self::Practice {
+                speed: 1.0,
+                seed: None,
+                reveal_sequence: false,
+            }),
+            "dig" => Some(Self::Dig(DigSettings::default())),
+            "combo" => Some(Self::Combo),
+            _ => None,
+        }
+    }
+
+    /// The name the mode is picked with on the command line
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Marathon => "marathon",
+            Self::Practice { .. } => "practice",
+            Self::Dig(_) => "dig",
+            Self::Combo => "combo",
+        }
+    }
+
+    /// Change the game speed, which is only allowed outside of ranked modes
+    pub fn with_speed(self, speed: f32) -> Result<Self, String> {
+        match self {
+            Self::Practice {
+                seed,
+                reveal_sequence,
+                ..
+            } if SPEED_RANGE.contains(&speed) => Ok(Self::Practice {
+                speed,
+                seed,
+                reveal_sequence,
+            }),
+            Self::Practice { .. } => Err(format!(
+                "the game speed must be between {}x and {}x",
+                SPEED_RANGE.start(),
+                SPEED_RANGE.end()
+            )),
+            Self::Marathon | Self::Dig(_) | Self::Combo => Err(String::from(
+                "the game speed can only be changed in practice mode",
+            )),
+        }
+    }
+
+    /// Fix the piece sequence to the seed, and whether to reveal it on the pause screen. Only
+    /// allowed in practice mode
+    pub fn with_seed(self, seed: Option<u64>, reveal_sequence: bool) -> Result<Self, String> {
+        match self {
+            Self::Practice { speed, .. } => Ok(Self::Practice {
+                speed,
+                seed,
+                reveal_sequence,
+            }),
+            Self::Marathon | Self::Dig(_) | Self::Combo => Err(String::from(
+                "the piece sequence can only be seeded or revealed in practice mode",
+            )),
+        }
+    }
+
+    /// The seed for the piece sequence, if it has been fixed
+    pub const fn seed(self) -> Option<u64> {
+        match self {
+            Self::Practice { seed, .. } => seed,
+            Self::Marathon | Self::Dig(_) | Self::Combo => None,
+        }
+    }
+
+    /// The number of simulation ticks to run every frame
+    pub const fn speed(self) -> f32 {
+        match self {
+            Self::Marathon | Self::Dig(_) | Self::Combo => 1.0,
+            Self::Practice { speed, .. } => speed,
+        }
+    }
+}
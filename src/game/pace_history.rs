@@ -0,0 +1,213 @@
+use super::Ruleset;
+use crate::config::Config;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How many of the latest games in the same mode the average pace is taken over
+const RECENT_GAME_COUNT: usize = 10;
+
+/// The rule modifiers a game was played with, e.g. `relaxed+frenzy`, so games are only compared
+/// with others played the same way
+pub fn modifiers(rules: &Ruleset, speed: f32) -> String {
+    let mut modifiers = Vec::new();
+    for (name, enabled) in [
+        ("relaxed", rules.relaxed),
+        ("frenzy", rules.frenzy),
+        ("contamination", rules.contamination),
+        ("cascade", rules.cascade_gravity),
+    ] {
+        if enabled {
+            modifiers.push(String::from(name));
+        }
+    }
+    if (speed - 1.0).abs() > f32::EPSILON {
+        modifiers.push(format!("{speed}x"));
+    }
+
+    if modifiers.is_empty() {
+        String::from("none")
+    } else {
+        modifiers.join("+")
+    }
+}
+
+/// How fast a finished game was played, kept so later games can be compared against it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaceRecord {
+    /// When the game ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The name of the game mode
+    pub mode: String,
+    /// The rule modifiers the game was played with, from [`modifiers`]
+    pub modifiers: String,
+    /// The game time in milliseconds, not counting pauses
+    pub game_time_ms: u64,
+    pub pieces_placed: usize,
+    pub lines_cleared: usize,
+}
+
+impl PaceRecord {
+    pub fn new(
+        mode: &str,
+        modifiers: String,
+        game_time: Duration,
+        pieces_placed: usize,
+        lines_cleared: usize,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            mode: String::from(mode),
+            modifiers,
+            game_time_ms: game_time.as_millis() as u64,
+            pieces_placed,
+            lines_cleared,
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let [timestamp, mode, modifiers, game_time_ms, pieces_placed, lines_cleared] =
+            line.split(',').map(str::trim).collect::<Vec<_>>()[..]
+        else {
+            return None;
+        };
+
+        Some(Self {
+            timestamp: timestamp.parse().ok()?,
+            mode: String::from(mode),
+            // Older versions only wrote whether the game was relaxed
+            modifiers: String::from(match modifiers {
+                "0" => "none",
+                "1" => "relaxed",
+                modifiers => modifiers,
+            }),
+            game_time_ms: game_time_ms.parse().ok()?,
+            pieces_placed: pieces_placed.parse().ok()?,
+            lines_cleared: lines_cleared.parse().ok()?,
+        })
+    }
+}
+
+/// The average pace of recent games, to show how the current game compares
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pace {
+    pub pieces_per_second: f64,
+    pub lines_per_second: f64,
+}
+
+impl Pace {
+    /// The average over the latest games in the mode with the same modifiers, or `None` if there
+    /// aren't any yet
+    pub fn average(records: &[PaceRecord], mode: &str, modifiers: &str) -> Option<Self> {
+        let recent: Vec<&PaceRecord> = records
+            .iter()
+            .rev()
+            .filter(|record| record.mode == mode && record.modifiers == modifiers)
+            .take(RECENT_GAME_COUNT)
+            .collect();
+
+        let seconds: f64 = recent
+            .iter()
+            .map(|record| record.game_time_ms as f64 / 1000.0)
+            .sum();
+        if seconds <= 0.0 {
+            return None;
+        }
+
+        let pieces: usize = recent.iter().map(|record| record.pieces_placed).sum();
+        let lines: usize = recent.iter().map(|record| record.lines_cleared).sum();
+        Some(Self {
+            pieces_per_second: pieces as f64 / seconds,
+            lines_per_second: lines as f64 / seconds,
+        })
+    }
+}
+
+/// The history file, `pace_history.csv` in the config directory
+fn path() -> Option<PathBuf> {
+    Config::directory().map(|dir| dir.join("pace_history.csv"))
+}
+
+/// Read every recorded game, skipping any lines that can't be understood
+pub fn load() -> Vec<PaceRecord> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().filter_map(PaceRecord::parse).collect())
+        .unwrap_or_default()
+}
+
+/// Add the game to the end of the history file
+pub fn append(record: &PaceRecord) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::other("no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{},{},{},{},{},{}",
+        record.timestamp,
+        record.mode,
+        record.modifiers,
+        record.game_time_ms,
+        record.pieces_placed,
+        record.lines_cleared
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(modifiers: &str, pieces_placed: usize) -> PaceRecord {
+        PaceRecord {
+            timestamp: 0,
+            mode: String::from("marathon"),
+            modifiers: String::from(modifiers),
+            game_time_ms: 10_000,
+            pieces_placed,
+            lines_cleared: 0,
+        }
+    }
+
+    #[test]
+    fn modifiers_name_every_rule_change() {
+        assert_eq!(modifiers(&Ruleset::modern(), 1.0), "none");
+
+        let rules = Ruleset {
+            frenzy: true,
+            cascade_gravity: true,
+            ..Ruleset::modern().relaxed()
+        };
+        assert_eq!(modifiers(&rules, 1.5), "relaxed+frenzy+cascade+1.5x");
+    }
+
+    #[test]
+    fn games_are_only_compared_with_the_same_modifiers() {
+        let records = [
+            record("none", 10),
+            record("frenzy", 30),
+            record("frenzy+contamination", 50),
+        ];
+        let pace = Pace::average(&records, "marathon", "frenzy").expect("No frenzy games found");
+        assert!((pace.pieces_per_second - 3.0).abs() < f64::EPSILON);
+        assert_eq!(Pace::average(&records, "marathon", "cascade"), None);
+    }
+
+    #[test]
+    fn records_from_older_versions_keep_whether_they_were_relaxed() {
+        let parsed = ["0,marathon,0,10000,10,0", "0,marathon,1,10000,10,0"]
+            .map(|line| PaceRecord::parse(line).map(|record| record.modifiers));
+        assert_eq!(
+            parsed,
+            [Some(String::from("none")), Some(String::from("relaxed"))]
+        );
+    }
+}
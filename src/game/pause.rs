@@ -1,8 +1,13 @@
-use console_input::keypress::exit_raw_mode;
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
-pub fn pause() {
+/// Wait for the player to unpause, showing the extra lines below the pause message
+///
+/// Returns true if they quit instead
+pub fn pause(details: &[String]) -> bool {
     println!("-- Paused (Esc to unpause) --\r");
+    for line in details {
+        println!("{line}\r");
+    }
     loop {
         let pressed_key = Some(read().expect("Failed to read input"));
         if let Some(Event::Key(event_key)) = pressed_key {
@@ -11,13 +16,13 @@ pub fn pause() {
                     code: KeyCode::Esc,
                     kind: KeyEventKind::Press,
                     ..
-                } => break,
+                } => return false,
                 KeyEvent {
                     code: KeyCode::Char('c'), // Close
                     modifiers: KeyModifiers::CONTROL,
                     kind: KeyEventKind::Press,
                     ..
-                } => exit_raw_mode(),
+                } => return true,
                 _ => (),
             }
         }
@@ -0,0 +1,151 @@
+use super::block_manager::BlockType;
+use gemini_engine::core::Colour;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fmt,
+};
+
+/// How many colours the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourDepth {
+    TrueColour,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColourDepth {
+    /// Guess the colour depth from the `COLORTERM` and `TERM` environment variables
+    pub fn detect() -> Self {
+        let colour_term = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+
+        if colour_term == "truecolor" || colour_term == "24bit" || cfg!(windows) {
+            Self::TrueColour
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    /// Approximate the colour the terminal would actually show
+    const fn reduce(self, colour: Colour) -> (u8, u8, u8) {
+        match self {
+            Self::TrueColour => (colour.r, colour.g, colour.b),
+            // The 6x6x6 colour cube
+            Self::Ansi256 => (
+                ((colour.r as u16 * 5 + 127) / 255) as u8,
+                ((colour.g as u16 * 5 + 127) / 255) as u8,
+                ((colour.b as u16 * 5 + 127) / 255) as u8,
+            ),
+            Self::Ansi16 => (colour.r / 128, colour.g / 128, colour.b / 128),
+        }
+    }
+}
+
+impl fmt::Display for ColourDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TrueColour => write!(f, "true colour"),
+            Self::Ansi256 => write!(f, "256 colours"),
+            Self::Ansi16 => write!(f, "16 colours"),
+        }
+    }
+}
+
+/// Piece colours chosen in the `[colours]` section of the config file, replacing the defaults
+#[derive(Debug, Clone, Default)]
+pub struct PieceColours {
+    overrides: HashMap<BlockType, Colour>,
+    /// Whether each piece is filled with its own pattern as well as its colour
+    pub patterns: bool,
+}
+
+impl PieceColours {
+    /// Read the overrides keyed by piece letter, with colours written as `#rrggbb`
+    ///
+    /// Returns a description of every entry that couldn't be understood
+    pub fn apply_overrides(&mut self, overrides: &BTreeMap<String, String>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for (piece_name, colour) in overrides {
+            let Some(piece) = BlockType::from_name(piece_name) else {
+                errors.push(format!("unknown piece `{piece_name}`"));
+                continue;
+            };
+
+            match parse_hex_colour(colour) {
+                Some(colour) => {
+                    self.overrides.insert(piece, colour);
+                }
+                None => errors.push(format!(
+                    "`{colour}` for the {} piece isn't a colour in the form #rrggbb",
+                    piece.letter()
+                )),
+            }
+        }
+
+        errors
+    }
+
+    /// The pattern to fill the piece with, if patterns are turned on
+    pub const fn pattern(&self, piece: BlockType) -> Option<char> {
+        if self.patterns {
+            Some(piece.pattern())
+        } else {
+            None
+        }
+    }
+
+    /// The colour the piece should be drawn in, if it has been overridden
+    pub fn get(&self, piece: BlockType) -> Option<Colour> {
+        self.overrides.get(&piece).copied()
+    }
+
+    /// Describe every pair of pieces that would look the same at the terminal's colour depth
+    pub fn find_clashes(&self, depth: ColourDepth) -> Vec<String> {
+        // Patterns keep the pieces apart even if their colours look the same
+        if self.patterns {
+            return Vec::new();
+        }
+
+        let colours: Vec<(BlockType, (u8, u8, u8))> = BlockType::ALL_VARIANTS
+            .into_iter()
+            .map(|piece| {
+                let colour = self.get(piece).unwrap_or_else(|| piece.default_colour());
+                (piece, depth.reduce(colour))
+            })
+            .collect();
+
+        let mut clashes = Vec::new();
+        for (i, (piece, colour)) in colours.iter().enumerate() {
+            for (other_piece, other_colour) in &colours[i + 1..] {
+                let overridden = self.get(*piece).is_some() || self.get(*other_piece).is_some();
+                if overridden && colour == other_colour {
+                    clashes.push(format!(
+                        "the {} and {} pieces look the same with this terminal's {depth}",
+                        piece.letter(),
+                        other_piece.letter()
+                    ));
+                }
+            }
+        }
+
+        clashes
+    }
+}
+
+fn parse_hex_colour(colour: &str) -> Option<Colour> {
+    let hex = colour.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Colour::rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Write a colour as `#rrggbb`
+pub fn hex_colour(colour: Colour) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.r, colour.g, colour.b)
+}
@@ -0,0 +1,16 @@
+use super::{AlertSettings, BellSettings, PieceColours, Quirks};
+use crate::sync::SyncSettings;
+
+/// Settings that change how the game looks, sounds and saves without affecting how it plays
+#[derive(Debug, Clone, Default)]
+pub struct Preferences {
+    pub bell: BellSettings,
+    pub piece_colours: PieceColours,
+    pub alerts: AlertSettings,
+    pub quirks: Quirks,
+    pub sync: SyncSettings,
+    /// Show how the game's pace compares to recent games while playing
+    pub pace_overlay: bool,
+    /// Draw a thin border between locked pieces in the stack
+    pub connected_pieces: bool,
+}
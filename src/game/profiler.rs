@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+/// Records how long each phase of every frame takes to a Chrome trace file, which can be opened
+/// in speedscope, Perfetto or `chrome://tracing` to track down stutters
+///
+/// Events are written as they happen, so most of the trace survives the game crashing. The event
+/// list is closed when the profiler is dropped, which the game does before quitting
+pub struct Profiler {
+    file: BufWriter<File>,
+    start: Instant,
+    /// Whether any event has been written yet, since JSON doesn't allow a comma before the first
+    has_events: bool,
+}
+
+impl Profiler {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "[")?;
+
+        Ok(Self {
+            file,
+            start: Instant::now(),
+            has_events: false,
+        })
+    }
+
+    /// Record a phase that started at `start` and has just finished
+    pub fn record(&mut self, phase: &str, start: Instant) {
+        let timestamp = start.duration_since(self.start).as_secs_f64() * 1_000_000.0;
+        let duration = start.elapsed().as_secs_f64() * 1_000_000.0;
+        let separator = if self.has_events { ",\n" } else { "" };
+        self.has_events = true;
+        // A failed write only loses part of the trace, which isn't worth stopping the game for
+        let _ = write!(
+            self.file,
+            r#"{separator}{{"name":"{phase}","ph":"X","ts":{timestamp:.3},"dur":{duration:.3},"pid":1,"tid":1}}"#
+        );
+    }
+
+    /// Record a whole frame that started at `start` and write everything recorded out to the file
+    pub fn end_frame(&mut self, start: Instant) {
+        self.record("frame", start);
+        let _ = self.file.flush();
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        let _ = writeln!(self.file, "\n]");
+        let _ = self.file.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    #[test]
+    fn the_event_list_is_closed_once_the_profiler_is_dropped() {
+        let directory = TempDir::new();
+        let path = directory.0.join("trace.json");
+
+        let mut profiler = Profiler::create(&path).expect("Failed to create the trace");
+        profiler.record("input", Instant::now());
+        profiler.end_frame(Instant::now());
+        drop(profiler);
+
+        let trace = fs::read_to_string(&path).expect("Failed to read the trace");
+        assert!(trace.starts_with("[\n{"));
+        assert!(trace.ends_with("}\n]\n"));
+        assert_eq!(trace.matches("},\n{").count(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+use gemini_engine::{
+    core::{CanDraw, Canvas, ColChar, Vec2D},
+    view::View,
+};
+use std::{env, process::Command};
+
+/// Workarounds for terminals that can't show the game properly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// Draw block characters like `█` and `░` as ASCII, for terminals whose fonts are missing
+    /// them or draw them two columns wide, which breaks up the double width board
+    pub ascii_blocks: bool,
+    /// Clear the screen before drawing every frame, for terminals that otherwise leave stale
+    /// characters behind. Turning this off helps terminals where clearing is slow
+    pub clear_each_frame: bool,
+}
+
+impl Quirks {
+    /// The names the quirks are turned on and off by with `--quirks`
+    const NAMES: [&'static str; 2] = ["ascii-blocks", "clear-each-frame"];
+
+    /// Pick the workarounds needed by the current terminal, going by `$TERM` and the platform
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+
+        Self {
+            // The Linux console and older terminals only have the fonts for ASCII
+            ascii_blocks: term == "linux" || term == "dumb" || term.starts_with("vt"),
+            // The Windows console keeps the previous frame's characters where nothing is drawn
+            clear_each_frame: cfg!(windows),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "ascii-blocks" => Some(&mut self.ascii_blocks),
+            "clear-each-frame" => Some(&mut self.clear_each_frame),
+            _ => None,
+        }
+    }
+
+    /// Turn quirks on or off from a comma separated list, e.g. `ascii-blocks,-clear-each-frame`
+    ///
+    /// Returns a description of every entry that couldn't be understood
+    pub fn apply_overrides(&mut self, overrides: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for entry in overrides
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (name, enabled) = entry
+                .strip_prefix('-')
+                .map_or((entry, true), |name| (name, false));
+            match self.get_mut(name) {
+                Some(quirk) => *quirk = enabled,
+                None => errors.push(format!(
+                    "unknown quirk `{name}`, expected one of {}",
+                    Self::NAMES.join(", ")
+                )),
+            }
+        }
+
+        errors
+    }
+}
+
+/// A terminal multiplexer the game is running inside, which has to pass the terminal's escape
+/// sequences through to the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    pub fn detect() -> Option<Self> {
+        if env::var_os("TMUX").is_some() {
+            Some(Self::Tmux)
+        } else if env::var_os("STY").is_some() {
+            Some(Self::Screen)
+        } else {
+            None
+        }
+    }
+
+    /// Describe the input events the multiplexer won't pass through to the game, or holds back,
+    /// and how to change that
+    pub fn input_notes(self) -> Vec<String> {
+        match self {
+            Self::Tmux => {
+                let mut notes = Vec::new();
+                if let Some(escape_time) = tmux_option("escape-time")
+                    .and_then(|escape_time| escape_time.parse::<u32>().ok())
+                    .filter(|&escape_time| escape_time > 50)
+                {
+                    notes.push(format!(
+                        "tmux holds back Esc for {escape_time}ms, so pausing will lag until `set -s escape-time 10`"
+                    ));
+                }
+                if tmux_option("focus-events").as_deref() != Some("on") {
+                    notes.push(String::from(
+                        "tmux won't pass focus changes through until `set -s focus-events on`",
+                    ));
+                }
+                if tmux_option("extended-keys").as_deref() == Some("off") {
+                    notes.push(String::from(
+                        "tmux reports some key combinations as plain keys until `set -s extended-keys on`",
+                    ));
+                }
+                notes
+            }
+            Self::Screen => vec![String::from(
+                "screen doesn't pass focus changes or extended key combinations through",
+            )],
+        }
+    }
+}
+
+/// Read one of tmux's server options, if tmux can be asked
+fn tmux_option(name: &str) -> Option<String> {
+    let output = Command::new("tmux")
+        .args(["show-options", "-sv", name])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Draw the element at double width, like the board and pieces, with any workarounds applied
+pub fn draw_cells(view: &mut View, quirks: Quirks, element: &impl CanDraw) {
+    view.draw_double_width(&Quirked::new(element, quirks));
+}
+
+/// Draws an element with its block characters swapped for ASCII if the terminal needs it
+pub struct Quirked<'a, E: CanDraw> {
+    element: &'a E,
+    quirks: Quirks,
+}
+
+impl<'a, E: CanDraw> Quirked<'a, E> {
+    pub const fn new(element: &'a E, quirks: Quirks) -> Self {
+        Self { element, quirks }
+    }
+}
+
+impl<E: CanDraw> CanDraw for Quirked<'_, E> {
+    fn draw_to(&self, canvas: &mut impl Canvas) {
+        if self.quirks.ascii_blocks {
+            self.element.draw_to(&mut AsciiCanvas { canvas });
+        } else {
+            self.element.draw_to(canvas);
+        }
+    }
+}
+
+struct AsciiCanvas<'a, C: Canvas> {
+    canvas: &'a mut C,
+}
+
+impl<C: Canvas> Canvas for AsciiCanvas<'_, C> {
+    fn plot(&mut self, pos: Vec2D, c: ColChar) {
+        self.canvas.plot(pos, c.with_char(ascii_char(c.text_char)));
+    }
+}
+
+/// The ASCII character to draw in place of a block character. None of them are used by the piece
+/// patterns, so every piece can still be told apart
+const fn ascii_char(text_char: char) -> char {
+    match text_char {
+        '█' => '=',
+        '▓' => '&',
+        '▒' => ':',
+        '░' => '-',
+        '·' => '.',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::block_manager::BlockType;
+    use std::collections::HashSet;
+
+    #[test]
+    fn ascii_blocks_keep_pieces_apart() {
+        let drawn: HashSet<char> = BlockType::ALL_VARIANTS
+            .iter()
+            .map(|piece| ascii_char(piece.pattern()))
+            .collect();
+        assert_eq!(drawn.len(), 7, "Two patterns look the same: {drawn:?}");
+        assert!(drawn.iter().all(char::is_ascii));
+
+        // Solid pieces, garbage and the ghost piece can't pass for the ASCII patterns either
+        for block in ['█', '▓', '▒', '░', '·'] {
+            assert!(
+                !['#', '@', '%'].contains(&ascii_char(block)),
+                "{block} is drawn like a pattern"
+            );
+        }
+    }
+
+    #[test]
+    fn overrides_turn_quirks_on_and_off() {
+        let mut quirks = Quirks {
+            ascii_blocks: false,
+            clear_each_frame: true,
+        };
+        let errors = quirks.apply_overrides(" ascii-blocks, -clear-each-frame,,wide-chars");
+
+        assert_eq!(
+            quirks,
+            Quirks {
+                ascii_blocks: true,
+                clear_each_frame: false,
+            }
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("wide-chars"));
+    }
+}
@@ -0,0 +1,122 @@
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    terminal::{Clear, ClearType},
+};
+use gemini_engine::view::View;
+use std::{
+    io::Write,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+/// A finished frame, and whether to clear the screen before writing it
+struct Frame {
+    text: String,
+    clear_first: bool,
+}
+
+#[derive(Default)]
+struct State {
+    /// The newest frame that hasn't been written yet
+    pending: Option<Frame>,
+    /// Whether a frame is being written right now
+    writing: bool,
+    /// Set once the game is done with the thread, so it can stop
+    closed: bool,
+}
+
+/// The state shared with the render thread, along with a signal for whenever it changes
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Writes finished frames to the terminal on a thread of its own, so a slow terminal (e.g. over
+/// SSH or in tmux) can't hold up input handling and the simulation. Frames can be written to any
+/// other output instead, e.g. an in-memory buffer in tests
+pub struct RenderThread {
+    shared: Arc<Shared>,
+}
+
+impl RenderThread {
+    pub fn spawn(output: impl Write + Send + 'static) -> Self {
+        let shared = Arc::new(Shared::default());
+        let thread_shared = Arc::clone(&shared);
+        thread::spawn(move || write_frames(&thread_shared, output));
+
+        Self { shared }
+    }
+
+    /// Send the view's current contents to be written. If the terminal hasn't caught up with the
+    /// previous frame yet, this one replaces it, so the newest frame is always the next written.
+    /// A replaced frame's screen clear is kept, so it's never skipped
+    pub fn submit(&self, view: &View, clear_first: bool) {
+        let text = view.to_string();
+        let mut state = self.shared.lock();
+        let clear_first = clear_first
+            || state
+                .pending
+                .as_ref()
+                .is_some_and(|frame| frame.clear_first);
+        state.pending = Some(Frame { text, clear_first });
+        drop(state);
+        self.shared.changed.notify_all();
+    }
+
+    /// Wait until every frame sent so far has been written, so something else can draw to the
+    /// terminal without being drawn over
+    pub fn flush(&self) {
+        let mut state = self.shared.lock();
+        while state.pending.is_some() || state.writing {
+            state = self.shared.wait(state);
+        }
+        drop(state);
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+fn write_frames(shared: &Shared, mut output: impl Write) {
+    loop {
+        let mut state = shared.lock();
+        while state.pending.is_none() && !state.closed {
+            state = shared.wait(state);
+        }
+        let Some(Frame { text, clear_first }) = state.pending.take() else {
+            return;
+        };
+        state.writing = true;
+        drop(state);
+
+        if clear_first {
+            let _ = queue!(output, MoveTo(0, 0), Clear(ClearType::FromCursorDown));
+        }
+        let _ = queue!(output, MoveTo(0, 0));
+        let _ = write!(output, "{text}");
+        let _ = output.flush();
+
+        shared.lock().writing = false;
+        shared.changed.notify_all();
+    }
+}
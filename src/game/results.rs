@@ -0,0 +1,267 @@
+use super::{
+    dig_history::DigRecord,
+    format_time,
+    messages::{Language, Message},
+    snapshot::GameSnapshot,
+    terminal,
+};
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind};
+use gemini_engine::{
+    containers::PixelContainer,
+    core::{ColChar, Modifier},
+};
+use std::{
+    fmt::Write,
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How many of the latest dig games the efficiency is averaged over
+const RECENT_GAME_COUNT: usize = 10;
+
+/// The ways the results can be saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnapshotFormat {
+    /// The board with ANSI colour codes, and the summary
+    Coloured,
+    /// The board and summary as plain text
+    Plain,
+    /// The full game state as TOML
+    State,
+}
+
+impl SnapshotFormat {
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Coloured => "ans",
+            Self::Plain => "txt",
+            Self::State => "toml",
+        }
+    }
+}
+
+/// A summary of a finished game
+pub struct Results {
+    pub score: i64,
+    /// Time spent playing, not counting pauses
+    pub game_time: Duration,
+    /// Time from the start of the game to the end, including pauses
+    pub real_time: Duration,
+    /// The names of the assists that were enabled during the game
+    pub assists: Vec<&'static str>,
+    /// Whether the game was played with the relaxed preset
+    pub relaxed: bool,
+    /// The number of times slow motion kicked in
+    pub slow_motion_triggers: usize,
+    /// The number of garbage rows left on the board in dig mode
+    pub garbage_left: Option<usize>,
+    /// The longest chain of clears in combo mode
+    pub longest_chain: Option<u32>,
+    /// The garbage cleared and pieces placed in dig mode
+    pub dig_record: Option<DigRecord>,
+    /// Earlier dig games on boards with the same amount of garbage, oldest first
+    pub dig_history: Vec<DigRecord>,
+    /// The hash of the piece sequence in seeded games, to show the game was played on the same
+    /// pieces as others with the seed
+    pub sequence_hash: Option<String>,
+    /// Anything that went wrong copying the saves to the sync folder after the game
+    pub sync_warnings: Vec<String>,
+    /// The walls and blocks left on the board
+    pub board: PixelContainer,
+    /// The full state of the game when it ended
+    pub state: GameSnapshot,
+}
+
+impl Results {
+    /// The lines describing how the game went
+    fn summary(&self) -> Vec<String> {
+        let mut lines = vec![
+            String::from(if self.garbage_left == Some(0) {
+                "All garbage cleared!"
+            } else {
+                "Game over!"
+            }),
+            format!("Score: {}", self.score),
+            format!(
+                "Game time: {} | Real time: {}",
+                format_time(self.game_time),
+                format_time(self.real_time)
+            ),
+        ];
+        if let Some(garbage_left) = self.garbage_left.filter(|rows| *rows > 0) {
+            lines.push(format!("Garbage rows left: {garbage_left}"));
+        }
+        if let Some(longest_chain) = self.longest_chain {
+            lines.push(format!("Longest combo: {longest_chain}"));
+        }
+        if let Some(record) = self.dig_record {
+            lines.push(format!(
+                "Efficiency: {:.2} garbage rows per piece",
+                record.efficiency()
+            ));
+
+            let recent: Vec<f64> = self
+                .dig_history
+                .iter()
+                .rev()
+                .take(RECENT_GAME_COUNT)
+                .map(DigRecord::efficiency)
+                .collect();
+            if let Some(best) = self
+                .dig_history
+                .iter()
+                .map(DigRecord::efficiency)
+                .reduce(f64::max)
+            {
+                lines.push(format!(
+                    "Best: {best:.2} | Average of last {}: {:.2}",
+                    recent.len(),
+                    recent.iter().sum::<f64>() / recent.len() as f64
+                ));
+            }
+        }
+
+        if let Some(sequence_hash) = &self.sequence_hash {
+            lines.push(format!("Sequence hash: {sequence_hash}"));
+        }
+
+        if self.relaxed {
+            lines.push(String::from("RELAXED GAME"));
+        }
+        if !self.assists.is_empty() {
+            lines.push(format!("ASSISTED GAME - {}", self.assists.join(", ")));
+            if self.slow_motion_triggers > 0 {
+                lines.push(Language::default().format(Message::SlowMotionTriggers {
+                    count: self.slow_motion_triggers,
+                }));
+            }
+        }
+
+        lines
+    }
+
+    /// Draw the board at double width like in game, with ANSI colour codes if `coloured` is set
+    fn board_snapshot(&self, coloured: bool) -> String {
+        let pixels = &self.board.pixels;
+        let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+            pixels.iter().map(|pixel| pixel.pos.x).min(),
+            pixels.iter().map(|pixel| pixel.pos.x).max(),
+            pixels.iter().map(|pixel| pixel.pos.y).min(),
+            pixels.iter().map(|pixel| pixel.pos.y).max(),
+        ) else {
+            return String::new();
+        };
+
+        let width = (max_x - min_x + 1) as usize;
+        let mut grid: Vec<Option<ColChar>> = vec![None; width * (max_y - min_y + 1) as usize];
+        for pixel in pixels {
+            grid[(pixel.pos.y - min_y) as usize * width + (pixel.pos.x - min_x) as usize] =
+                Some(pixel.fill_char);
+        }
+
+        let mut snapshot = String::new();
+        for row in grid.chunks(width) {
+            for cell in row {
+                let Some(fill_char) = cell else {
+                    snapshot.push_str("  ");
+                    continue;
+                };
+
+                let escape_code = match fill_char.modifier {
+                    _ if !coloured => None,
+                    Modifier::Colour(colour) => {
+                        Some(format!("\x1b[38;2;{};{};{}m", colour.r, colour.g, colour.b))
+                    }
+                    Modifier::Coded(code) => Some(format!("\x1b[{code}m")),
+                    Modifier::None => None,
+                };
+                if let Some(escape_code) = escape_code {
+                    let _ = write!(snapshot, "{escape_code}{0}{0}\x1b[0m", fill_char.text_char);
+                } else {
+                    snapshot.push(fill_char.text_char);
+                    snapshot.push(fill_char.text_char);
+                }
+            }
+            snapshot.push('\n');
+        }
+
+        snapshot
+    }
+
+    /// The board followed by the summary, with ANSI colour codes if `coloured` is set
+    pub fn to_text(&self, coloured: bool) -> String {
+        let mut contents = self.board_snapshot(coloured);
+        for line in self.summary() {
+            contents.push('\n');
+            contents.push_str(&line);
+        }
+        contents.push('\n');
+        contents
+    }
+
+    /// Write the board and summary to a file in the current directory, returning its name
+    fn save_snapshot(&self, format: SnapshotFormat) -> std::io::Result<String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = format!("console-tetris-{timestamp}.{}", format.extension());
+
+        let contents = match format {
+            SnapshotFormat::State => self.state.to_toml(),
+            SnapshotFormat::Coloured | SnapshotFormat::Plain => {
+                self.to_text(format == SnapshotFormat::Coloured)
+            }
+        };
+
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Print the results, offer to save a snapshot of them and close the game
+    pub fn show(&self) -> ! {
+        for line in self.summary() {
+            println!("{line}\r");
+        }
+
+        #[cfg(feature = "update-check")]
+        if let Some(version) = crate::update_check::newer_version() {
+            println!("\r");
+            println!("console-tetris {version} is available\r");
+        }
+
+        if !self.sync_warnings.is_empty() {
+            println!("\r");
+            for warning in &self.sync_warnings {
+                println!("Sync: {warning}\r");
+            }
+        }
+
+        println!("\r");
+        println!(
+            "S to save a snapshot | T to save it as plain text | G to save the game state | any other key to quit\r"
+        );
+        let format = loop {
+            if let Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = read().expect("Failed to read input")
+            {
+                match code {
+                    KeyCode::Char('s' | 'S') => break SnapshotFormat::Coloured,
+                    KeyCode::Char('t' | 'T') => break SnapshotFormat::Plain,
+                    KeyCode::Char('g' | 'G') => break SnapshotFormat::State,
+                    _ => terminal::exit(),
+                }
+            }
+        };
+
+        match self.save_snapshot(format) {
+            Ok(path) => println!("Saved to {path}\r"),
+            Err(error) => println!("Failed to save snapshot: {error}\r"),
+        }
+
+        terminal::exit()
+    }
+}
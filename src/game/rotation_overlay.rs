@@ -0,0 +1,66 @@
+use super::block_manager::{tetris_core::RotationAttempt, Block};
+use gemini_engine::{
+    containers::PixelContainer,
+    core::{ColChar, Colour, Vec2D},
+    primitives::Pixel,
+};
+
+/// Mark the block's rotation centre, and every position its last rotation tried to move the centre
+/// to, numbered in the order the kicks were tried. The kick that was used is shown in green
+pub fn rotation_overlay(block: &Block, attempt: Option<&RotationAttempt>) -> PixelContainer {
+    let mut overlay = PixelContainer::new();
+
+    if let Some(attempt) = attempt {
+        for (i, kick) in attempt.kicks.iter().enumerate() {
+            let colour = if attempt.accepted == Some(i) {
+                Colour::rgb(0, 255, 0)
+            } else {
+                Colour::rgb(255, 0, 0)
+            };
+            let number = char::from_digit(i as u32 + 1, 10).unwrap_or('?');
+            overlay.push(Pixel::new(
+                attempt.origin + *kick,
+                ColChar::EMPTY.with_char(number).with_colour(colour),
+            ));
+        }
+    }
+
+    overlay.push(Pixel::new(
+        block.pos,
+        ColChar::EMPTY
+            .with_char('+')
+            .with_colour(Colour::rgb(255, 255, 255)),
+    ));
+
+    overlay
+}
+
+/// Describe the kick the last rotation used, short enough to fit beside the board
+pub fn describe_kick(attempt: Option<&RotationAttempt>) -> String {
+    let Some(attempt) = attempt.filter(|attempt| !attempt.kicks.is_empty()) else {
+        return String::from("Rotate to see kicks");
+    };
+
+    match attempt.accepted {
+        None => format!("No kick fit ({} tried)", attempt.kicks.len()),
+        Some(i) if attempt.kicks[i] == Vec2D::ZERO => String::from("Rotated without a kick"),
+        Some(i) => {
+            let kick = attempt.kicks[i];
+            let mut moves = Vec::new();
+            if kick.x != 0 {
+                let direction = if kick.x < 0 { "left" } else { "right" };
+                moves.push(format!("{} {direction}", kick.x.abs()));
+            }
+            if kick.y != 0 {
+                let direction = if kick.y < 0 { "up" } else { "down" };
+                moves.push(format!("{} {direction}", kick.y.abs()));
+            }
+            format!(
+                "Kick {}/{}: {}",
+                i + 1,
+                attempt.kicks.len(),
+                moves.join(", ")
+            )
+        }
+    }
+}
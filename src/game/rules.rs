@@ -0,0 +1,118 @@
+use super::{
+    rotation_system::{self, Srs},
+    GravityCurve, RotationSystem,
+};
+use serde::{Deserialize, Serialize};
+
+/// How upcoming pieces are picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Randomizer {
+    /// Deal out shuffled bags containing one of every piece
+    Bag,
+    /// Pick each piece independently, rerolling once if it repeats the previous piece, like the
+    /// NES version
+    Classic,
+}
+
+/// How line clears are scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scoring {
+    /// Fixed scores for line clears and T-spins
+    Modern,
+    /// The NES line clear scores multiplied by the level, with no T-spin bonuses
+    Classic,
+}
+
+/// The rules that change how the game itself plays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Ruleset {
+    /// The gravity and lock delay at every level
+    pub gravity_curve: GravityCurve,
+    /// How many upcoming pieces are shown
+    pub piece_preview_count: usize,
+    /// How many pieces can be held at once. Pressing hold again with every slot full swaps the
+    /// active piece with the oldest held one. Holding is disabled if this is 0
+    pub hold_slots: usize,
+    /// Whether hard and sonic drops are allowed
+    pub hard_drop: bool,
+    /// Whether the ghost piece is shown
+    pub ghost_piece: bool,
+    pub randomizer: Randomizer,
+    pub scoring: Scoring,
+    #[serde(with = "rotation_system::by_name")]
+    pub rotation_system: &'static dyn RotationSystem,
+    /// Whether the relaxed preset was applied, so relaxed games are only compared with each other
+    #[serde(default)]
+    pub relaxed: bool,
+    /// Whether two pieces fall at once, with the player swapping control between them
+    #[serde(default)]
+    pub frenzy: bool,
+    /// Whether locked cells can turn into cracked cells that take two clears to remove, or bombs
+    /// that clear the cells around them
+    #[serde(default)]
+    pub contamination: bool,
+    /// Whether groups of blocks left floating by a clear fall until they land, possibly clearing
+    /// more lines in a chain
+    #[serde(default)]
+    pub cascade_gravity: bool,
+}
+
+impl Ruleset {
+    /// The default rules
+    pub fn modern() -> Self {
+        Self {
+            gravity_curve: GravityCurve::default(),
+            piece_preview_count: 3,
+            hold_slots: 1,
+            hard_drop: true,
+            ghost_piece: true,
+            randomizer: Randomizer::Bag,
+            scoring: Scoring::Modern,
+            rotation_system: &Srs,
+            relaxed: false,
+            frenzy: false,
+            contamination: false,
+            cascade_gravity: false,
+        }
+    }
+
+    /// The rules of the NES version: no hold, hard drop or ghost piece, a single preview, NES
+    /// level speeds and scoring, and blocks that lock as soon as they land
+    pub fn classic() -> Self {
+        Self {
+            gravity_curve: GravityCurve::classic(),
+            piece_preview_count: 1,
+            hold_slots: 0,
+            hard_drop: false,
+            ghost_piece: false,
+            randomizer: Randomizer::Classic,
+            scoring: Scoring::Classic,
+            rotation_system: &Srs,
+            relaxed: false,
+            frenzy: false,
+            contamination: false,
+            cascade_gravity: false,
+        }
+    }
+
+    /// Make the rules more forgiving for players who need more time: gravity is capped at one cell
+    /// every half a second and pieces wait at least a second and a half before locking
+    pub fn relaxed(self) -> Self {
+        Self {
+            gravity_curve: self.gravity_curve.relaxed(),
+            relaxed: true,
+            ..self
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "modern" => Some(Self::modern()),
+            "classic" | "nes" => Some(Self::classic()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use super::{
+    block_manager::BlockType,
+    collision_manager::{CellKind, CollisionManager},
+    piece_colours::hex_colour,
+    Ruleset,
+};
+use gemini_engine::core::Modifier;
+use serde::{Deserialize, Serialize};
+
+/// The version of the snapshot format written by this build. Bump it whenever a field is renamed,
+/// removed or changes meaning, so older snapshots can be told apart from newer ones
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Everything needed to pick a game back up, in a form that can be saved and read back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub version: u32,
+    pub score: i64,
+    pub lines_cleared: i64,
+    pub pieces_placed: usize,
+    pub rules: Ruleset,
+    pub piece: PieceSnapshot,
+    /// Upcoming pieces, with the next one first
+    pub queue: Vec<BlockType>,
+    /// Held pieces, oldest first
+    pub held: Vec<BlockType>,
+    /// Every placed block and garbage cell on the board
+    pub board: Vec<CellSnapshot>,
+}
+
+impl GameSnapshot {
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("Every snapshot field can be written as TOML")
+    }
+}
+
+/// The active piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceSnapshot {
+    pub shape: BlockType,
+    pub x: i64,
+    pub y: i64,
+    pub rotation: usize,
+}
+
+/// One filled cell of the board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellSnapshot {
+    pub x: i64,
+    pub y: i64,
+    /// The cell's colour as `#rrggbb`, if it has one
+    pub colour: Option<String>,
+    /// Whether the cell is cracked, a bomb or garbage, if it isn't a plain cell
+    pub kind: Option<CellKind>,
+}
+
+impl CellSnapshot {
+    /// Snapshot every cell of the board
+    pub fn from_board(board: &CollisionManager) -> Vec<Self> {
+        board
+            .stationary_blocks
+            .pixels
+            .iter()
+            .map(|pixel| Self {
+                x: pixel.pos.x,
+                y: pixel.pos.y,
+                colour: match pixel.fill_char.modifier {
+                    Modifier::Colour(colour) => Some(hex_colour(colour)),
+                    _ => None,
+                },
+                kind: board.cell_kind(pixel.pos),
+            })
+            .collect()
+    }
+}
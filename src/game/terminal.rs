@@ -0,0 +1,37 @@
+use console_input::keypress::exit_raw_mode;
+use crossterm::{
+    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute,
+    terminal::supports_keyboard_enhancement,
+};
+use std::{
+    io::stdout,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether the terminal was asked to report key repeats, so it can be set back when the game ends
+static REPORTING_REPEATS: AtomicBool = AtomicBool::new(false);
+
+/// Ask the terminal to mark the presses it repeats while a key is held down, if it supports the
+/// kitty keyboard protocol. Every key has to be sent as an escape code for letters and the space
+/// bar to be marked too
+///
+/// Returns whether key repeats will be reported
+pub fn report_key_repeats() -> bool {
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES;
+    let reporting = supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(stdout(), PushKeyboardEnhancementFlags(flags)).is_ok();
+    REPORTING_REPEATS.store(reporting, Ordering::Relaxed);
+
+    reporting
+}
+
+/// Set the terminal's keyboard reporting back the way it was, leave raw mode and exit
+pub fn exit() -> ! {
+    if REPORTING_REPEATS.swap(false, Ordering::Relaxed) {
+        let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+    exit_raw_mode()
+}
@@ -1,25 +1,322 @@
-use console_input::keypress::enable_raw_mode;
+use console_input::keypress::{enable_raw_mode, exit_raw_mode};
 use gemini_engine::gameloop::MainLoopRoot;
+use std::io::stdin;
+mod args;
+mod config;
+mod diagnostics;
 mod game;
-use game::Game;
-
-const BLOCK_PLACE_COOLDOWN: u32 = 30;
-const PIECE_PREVIEW_COUNT: usize = 3;
-const CONTROLS_HELP_TEXT: &str = "Controls:
-C to hold
-Left/Right to shift
-Space hard | Down soft
-Z AC | Up/X C rotation
-Esc to pause";
+mod profile;
+mod sync;
+#[cfg(test)]
+#[path = "../tests/support/mod.rs"]
+mod test_support;
+#[cfg(feature = "update-check")]
+mod update_check;
+use args::Args;
+use config::Config;
+use game::{
+    AlertSettings, BindingProblem, ColourDepth, Controls, Game, GameMode, GravityCurve,
+    InputRecorder, InputSettings, KeyboardLayout, Layout, PieceColours, Preferences, Profiler,
+    Quirks, Replay, Ruleset,
+};
 
 fn main() {
-    enable_raw_mode();
+    let args = Args::parse();
 
-    let mut game = Game::new(
-        BLOCK_PLACE_COOLDOWN,
-        PIECE_PREVIEW_COUNT,
-        CONTROLS_HELP_TEXT,
+    if args.latency_test {
+        enable_raw_mode();
+        diagnostics::input_latency_test();
+        exit_raw_mode();
+    }
+    if args.input_echo {
+        enable_raw_mode();
+        diagnostics::input_echo();
+        exit_raw_mode();
+    }
+
+    if profile::handle_args(&args) {
+        return;
+    }
+
+    let layout = args.layout.unwrap_or_else(KeyboardLayout::detect);
+    let mut warnings = Vec::new();
+
+    let config = match Config::load() {
+        Ok((config, notices)) => {
+            warnings.extend(notices);
+            config
+        }
+        Err(error) => {
+            warnings.push(error);
+            Config::default()
+        }
+    };
+    warnings.extend(config.sync.pull());
+    #[cfg(feature = "update-check")]
+    if config.check_for_updates {
+        update_check::spawn();
+    }
+
+    let mut mode = game_mode(&args, &config, &mut warnings);
+
+    let mut rules = ruleset(&args, &config, &mut warnings);
+
+    let mut controls = controls(&config, layout, &mut warnings);
+
+    let (input_recorder, replay) = input_log(
+        &args,
+        &mut mode,
+        &mut rules,
+        &mut controls.input,
+        &mut warnings,
     );
 
+    let mut assists = config.assists;
+    if assists.t_slot_hints && !matches!(mode, GameMode::Practice { .. }) {
+        assists.t_slot_hints = false;
+        warnings.push(String::from(
+            "T-slot hints are only available in practice mode",
+        ));
+    }
+
+    let mut piece_colours = PieceColours::default();
+    piece_colours.patterns = config.piece_patterns;
+    warnings.extend(piece_colours.apply_overrides(&config.colours));
+    warnings.extend(piece_colours.find_clashes(ColourDepth::detect()));
+
+    let alerts = config.alerts.validate().unwrap_or_else(|error| {
+        warnings.push(error);
+        AlertSettings::default()
+    });
+
+    let mut quirks = Quirks::detect();
+    if let Some(overrides) = &args.quirks {
+        warnings.extend(quirks.apply_overrides(overrides));
+    }
+
+    let profiler = args.trace.as_deref().and_then(|path| {
+        Profiler::create(path)
+            .map_err(|error| warnings.push(format!("failed to create {}: {error}", path.display())))
+            .ok()
+    });
+
+    let mut game = Game::new(
+        rules,
+        mode,
+        layout_for_terminal(args.layout(), &mut warnings),
+        controls,
+        assists,
+        Preferences {
+            bell: config.bell,
+            piece_colours,
+            alerts,
+            quirks,
+            sync: config.sync,
+            pace_overlay: config.pace_overlay,
+            connected_pieces: config.connected_pieces,
+        },
+    )
+    .with_profiler(profiler)
+    .with_input_log(input_recorder, replay);
+
+    pre_game_screen(&warnings, game.sequence_hash());
+    enable_raw_mode();
+
     game.main_loop();
 }
+
+/// The key bindings from the config, with the input settings. Bindings that won't work while
+/// playing are listed, and fixed if the player agrees
+fn controls(config: &Config, layout: KeyboardLayout, warnings: &mut Vec<String>) -> Controls {
+    let (input, input_warnings) = config.input.validate();
+    warnings.extend(input_warnings);
+    let default_controls = || {
+        if input.one_handed {
+            Controls::one_handed(layout)
+        } else {
+            Controls::new(layout)
+        }
+    };
+    let mut controls = default_controls();
+    warnings.extend(controls.apply_overrides(&config.bindings));
+    controls.input = input;
+    if controls.input.sticky_keys {
+        warnings.extend(controls.add_chord_free_bindings());
+    }
+    let binding_problems = controls.find_conflicts();
+    if !binding_problems.is_empty() && binding_problems_screen(&binding_problems) {
+        let fixed = controls.fix_conflicts(&binding_problems, &default_controls());
+        warnings.push(match config::save_bindings(&fixed) {
+            Ok(path) => format!(
+                "Fixed the key bindings in {}, the original was saved alongside with a .bak extension",
+                path.display()
+            ),
+            Err(error) => format!("Fixed the key bindings for this game only, since they couldn't be saved: {error}"),
+        });
+        for (action, keys) in &fixed {
+            if keys.is_empty() {
+                warnings.push(format!(
+                    "{action} was left without a key, since its default ones are taken"
+                ));
+            }
+        }
+    }
+
+    controls
+}
+
+/// The layout picked on the command line, or a smaller one if the terminal is too small for it
+fn layout_for_terminal(layout: Layout, warnings: &mut Vec<String>) -> Layout {
+    let Ok((columns, rows)) = crossterm::terminal::size() else {
+        return layout;
+    };
+
+    let (layout, warning) = layout.fit_to(columns, rows);
+    warnings.extend(warning);
+    layout
+}
+
+/// List the key bindings that won't work while playing and ask whether to fix them
+///
+/// Returns whether they should be fixed
+fn binding_problems_screen(problems: &[BindingProblem]) -> bool {
+    println!("Some of your key bindings won't work while playing:");
+    for problem in problems {
+        println!("  {problem}");
+    }
+    println!("Fix them automatically? The fixed bindings are saved to your config file [y/N]");
+
+    let mut answer = String::new();
+    stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read input");
+    answer.trim().eq_ignore_ascii_case("y")
+}
+
+/// Show any warnings before the game starts and wait for Enter to be pressed. Seeded games always
+/// wait, so racers can compare their sequence hashes to check they'll be dealt the same pieces
+fn pre_game_screen(warnings: &[String], sequence_hash: Option<&str>) {
+    if warnings.is_empty() && sequence_hash.is_none() {
+        return;
+    }
+
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if let Some(sequence_hash) = sequence_hash {
+        println!("Sequence hash of the first 50 pieces: {sequence_hash}");
+    }
+    println!("Press Enter to continue");
+    stdin()
+        .read_line(&mut String::new())
+        .expect("Failed to read input");
+}
+
+/// The game mode picked on the command line, with its settings from the config and arguments
+fn game_mode(args: &Args, config: &Config, warnings: &mut Vec<String>) -> GameMode {
+    let mut mode = args.mode.unwrap_or(GameMode::Marathon);
+    if let GameMode::Dig(settings) = &mut mode {
+        match config.dig.validate() {
+            Ok(dig_settings) => *settings = dig_settings,
+            Err(error) => warnings.push(error),
+        }
+    }
+    if args.seed.is_some() || args.reveal_sequence {
+        match mode.with_seed(args.seed, args.reveal_sequence) {
+            Ok(new_mode) => mode = new_mode,
+            Err(error) => warnings.push(error),
+        }
+    }
+    if let Some(speed) = args.speed {
+        match mode.with_speed(speed) {
+            Ok(new_mode) => mode = new_mode,
+            Err(error) => warnings.push(error),
+        }
+    }
+
+    mode
+}
+
+/// The input log to record the game to or replay it from. A replay's seed, speed, rules and sticky
+/// keys setting replace the ones picked for the game, so it plays out the same as the recording
+fn input_log(
+    args: &Args,
+    mode: &mut GameMode,
+    rules: &mut Ruleset,
+    input: &mut InputSettings,
+    warnings: &mut Vec<String>,
+) -> (Option<InputRecorder>, Option<Replay>) {
+    let replay = args.replay.as_deref().and_then(|path| {
+        Replay::load(path)
+            .map_err(|error| warnings.push(error))
+            .ok()
+    });
+    if args.record.is_none() && replay.is_none() {
+        return (None, None);
+    }
+
+    let seed = replay
+        .as_ref()
+        .map(|replay| replay.seed)
+        .or_else(|| mode.seed())
+        .unwrap_or_else(rand::random);
+    let Ok(seeded_mode) = mode.with_seed(Some(seed), args.reveal_sequence) else {
+        warnings.push(String::from(
+            "games can only be recorded or replayed in practice mode",
+        ));
+        return (None, None);
+    };
+    *mode = match replay
+        .as_ref()
+        .map(|replay| seeded_mode.with_speed(replay.speed))
+    {
+        Some(Ok(replay_mode)) => replay_mode,
+        Some(Err(error)) => {
+            warnings.push(error);
+            return (None, None);
+        }
+        None => seeded_mode,
+    };
+    if let Some(replay) = &replay {
+        *rules = replay.rules.clone();
+        input.sticky_keys = replay.sticky_keys;
+    }
+
+    let input_recorder = args.record.as_deref().and_then(|path| {
+        InputRecorder::create(path, seed, mode.speed(), input.sticky_keys, rules)
+            .map_err(|error| warnings.push(format!("failed to create {}: {error}", path.display())))
+            .ok()
+    });
+    (input_recorder, replay)
+}
+
+/// The ruleset picked on the command line, with the config's gravity curve and any overrides
+fn ruleset(args: &Args, config: &Config, warnings: &mut Vec<String>) -> Ruleset {
+    let mut rules = args.rules.clone().unwrap_or_else(Ruleset::modern);
+    if !config.gravity.is_empty() {
+        match GravityCurve::new(config.gravity.clone()) {
+            Ok(gravity_curve) => rules.gravity_curve = gravity_curve,
+            Err(error) => warnings.push(error),
+        }
+    }
+    if let Some(hold_slots) = args.hold_slots {
+        rules.hold_slots = hold_slots;
+    }
+    if let Some(rotation_system) = args.rotation_system {
+        rules.rotation_system = rotation_system;
+    }
+    if args.relaxed {
+        rules = rules.relaxed();
+    }
+    if args.frenzy {
+        rules.frenzy = true;
+    }
+    if args.contamination {
+        rules.contamination = true;
+    }
+    if args.cascade {
+        rules.cascade_gravity = true;
+    }
+
+    rules
+}
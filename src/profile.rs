@@ -0,0 +1,154 @@
+use crate::{args::Args, config::Config};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path, process};
+
+/// The version of the profile format this build writes
+const PROFILE_VERSION: u32 = 1;
+
+/// The files in the config directory that make up a profile: settings, key bindings and piece
+/// colours in the config, and the dig history for personal bests
+const PROFILE_FILES: [&str; 3] = ["config.toml", "dig_history.csv", "pace_history.csv"];
+
+/// A player's files bundled into one, to carry their setup to another computer
+#[derive(Debug, Serialize, Deserialize)]
+struct Profile {
+    version: u32,
+    /// The contents of every profile file that exists, keyed by file name
+    files: BTreeMap<String, String>,
+}
+
+/// Export or import a profile if asked to on the command line, printing how it went. Failures
+/// exit with an error code, so scripts can tell they didn't work
+///
+/// Returns true if there was one to export or import, in which case the game shouldn't start
+pub fn handle_args(args: &Args) -> bool {
+    if let Some(path) = &args.export_profile {
+        match export(path) {
+            Ok(files) => println!("Exported {} to {}", files.join(", "), path.display()),
+            Err(error) => fail(&format!("Failed to export profile: {error}")),
+        }
+    } else if let Some(path) = &args.import_profile {
+        match import(path) {
+            Ok(files) => println!("Imported {} from {}", files.join(", "), path.display()),
+            Err(error) => fail(&format!("Failed to import profile: {error}")),
+        }
+    } else {
+        return false;
+    }
+
+    true
+}
+
+/// Print the error to stderr and exit with an error code
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+/// Bundle the profile files into a single file at `path`
+///
+/// Returns the names of the files that were bundled
+fn export(path: &Path) -> Result<Vec<String>, String> {
+    let directory = Config::directory().ok_or("no config directory")?;
+
+    let mut files = BTreeMap::new();
+    for name in PROFILE_FILES {
+        match fs::read_to_string(directory.join(name)) {
+            Ok(contents) => {
+                files.insert(String::from(name), contents);
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => (),
+            Err(error) => return Err(format!("failed to read {name}: {error}")),
+        }
+    }
+
+    if files.is_empty() {
+        return Err(String::from(
+            "there are no settings or dig history to export yet",
+        ));
+    }
+
+    let profile = Profile {
+        version: PROFILE_VERSION,
+        files,
+    };
+    let contents = toml::to_string(&profile).map_err(|error| error.to_string())?;
+    fs::write(path, contents)
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+
+    Ok(profile.files.into_keys().collect())
+}
+
+/// Unpack a profile exported with [`export`] into the config directory. Files that would be
+/// replaced are kept as `<name>.bak`
+///
+/// Returns the names of the files that were unpacked
+fn import(path: &Path) -> Result<Vec<String>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    let profile: Profile = toml::from_str(&contents)
+        .map_err(|error| format!("failed to parse {}: {error}", path.display()))?;
+
+    if profile.version > PROFILE_VERSION {
+        return Err(format!(
+            "the profile is version {}, but only versions up to {PROFILE_VERSION} are understood",
+            profile.version
+        ));
+    }
+    // Only ever write the known files, so a profile can't put anything else on the system. The
+    // config's `[sync]` section is dropped from them below, since its commands are run by the shell
+    if let Some(name) = profile
+        .files
+        .keys()
+        .find(|name| !PROFILE_FILES.contains(&name.as_str()))
+    {
+        return Err(format!("the profile contains an unknown file, {name}"));
+    }
+
+    let directory = Config::directory().ok_or("no config directory")?;
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+    let mut imported = Vec::new();
+    for (name, contents) in &profile.files {
+        let file = directory.join(name);
+        let mut contents = contents.clone();
+        let note = if name == "config.toml" && strip_sync(&mut contents, &file)? {
+            " (without its [sync] section)"
+        } else {
+            ""
+        };
+
+        if file.exists() {
+            fs::copy(&file, directory.join(format!("{name}.bak")))
+                .map_err(|error| format!("failed to back up {name}: {error}"))?;
+        }
+        fs::write(&file, contents).map_err(|error| format!("failed to write {name}: {error}"))?;
+        imported.push(format!("{name}{note}"));
+    }
+
+    Ok(imported)
+}
+
+/// Replace the `[sync]` section of an imported config with the one in the config it's replacing,
+/// if any. Its sync commands are run by the shell when the game starts, so a profile from someone
+/// else could otherwise run anything
+///
+/// Returns true if the imported config had a `[sync]` section
+fn strip_sync(contents: &mut String, existing: &Path) -> Result<bool, String> {
+    let mut table: toml::Table = toml::from_str(contents)
+        .map_err(|error| format!("failed to parse the profile's config.toml: {error}"))?;
+    let had_sync = table.remove("sync").is_some();
+
+    let existing_sync = fs::read_to_string(existing)
+        .ok()
+        .and_then(|existing| toml::from_str::<toml::Table>(&existing).ok())
+        .and_then(|mut existing| existing.remove("sync"));
+    if !had_sync && existing_sync.is_none() {
+        return Ok(false);
+    }
+
+    if let Some(sync) = existing_sync {
+        table.insert(String::from("sync"), sync);
+    }
+    *contents = toml::to_string(&table).map_err(|error| error.to_string())?;
+    Ok(had_sync)
+}
@@ -0,0 +1,277 @@
+use crate::config::Config;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The save files that are synced. The config itself isn't, since it says where to sync to
+const SYNCED_FILES: [&str; 2] = ["dig_history.csv", "pace_history.csv"];
+
+/// The file in the config directory recording when the saves were last synced
+const LAST_SYNC_FILE: &str = "last_sync";
+
+/// The `[sync]` section of the config file, for keeping saves in a folder shared between
+/// computers
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// A folder kept in sync by another program, like Dropbox or Syncthing. Syncing is off if
+    /// this isn't set
+    pub directory: Option<PathBuf>,
+    /// A command run before reading from the folder, e.g. to download it with rclone
+    pub pull_command: Option<String>,
+    /// A command run after writing to the folder, e.g. to upload it with rclone
+    pub push_command: Option<String>,
+}
+
+impl SyncSettings {
+    /// Copy any saves that changed in the sync folder since the last sync into the config
+    /// directory. Should be called before the saves are read
+    ///
+    /// Returns warnings about anything that couldn't be synced
+    pub fn pull(&self) -> Vec<String> {
+        let (Some(remote_dir), Some(local_dir)) = (&self.directory, Config::directory()) else {
+            return Vec::new();
+        };
+        let mut warnings = Vec::new();
+        if let Some(command) = &self.pull_command {
+            warnings.extend(run(command).err());
+        }
+        warnings.extend(pull_saves(remote_dir, &local_dir));
+
+        warnings
+    }
+
+    /// Copy any saves that changed since the last sync out to the sync folder. Should be called
+    /// after the saves are written
+    ///
+    /// Returns warnings about anything that couldn't be synced
+    pub fn push(&self) -> Vec<String> {
+        let (Some(remote_dir), Some(local_dir)) = (&self.directory, Config::directory()) else {
+            return Vec::new();
+        };
+
+        let mut warnings = Vec::new();
+        match push_saves(remote_dir, &local_dir) {
+            Ok(conflicts) => warnings.extend(conflicts),
+            Err(error) => return vec![error],
+        }
+        if let Some(command) = &self.push_command {
+            warnings.extend(run(command).err());
+        }
+
+        warnings
+    }
+}
+
+/// Copy the saves that changed in the remote folder since the last sync into the local one,
+/// keeping the remote copy aside if both changed
+fn pull_saves(remote_dir: &Path, local_dir: &Path) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let last_sync = last_sync(local_dir);
+    for name in SYNCED_FILES {
+        let (local, remote) = (local_dir.join(name), remote_dir.join(name));
+        let Some(remote_modified) = modified(&remote) else {
+            continue;
+        };
+        let local_modified = modified(&local);
+
+        let result = match local_modified {
+            // Both copies changed since they were last synced, so neither can be trusted to
+            // replace the other
+            Some(local_modified) if local_modified > last_sync && remote_modified > last_sync => {
+                let conflict = local_dir.join(format!("{name}.conflict-{remote_modified}"));
+                warnings.push(format!(
+                    "{name} was changed both here and in the sync folder, so the synced copy was saved to {} instead",
+                    conflict.display()
+                ));
+                fs::copy(&remote, conflict)
+            }
+            Some(_) if remote_modified <= last_sync => continue,
+            _ => fs::copy(&remote, &local),
+        };
+        if let Err(error) = result {
+            warnings.push(format!("failed to sync {name}: {error}"));
+        }
+    }
+
+    warnings.extend(record_sync(local_dir).err());
+    warnings
+}
+
+/// Copy the saves that changed locally since the last sync out to the remote folder, keeping the
+/// local copy aside if the remote one changed as well
+///
+/// Returns a warning for every save kept aside, or an error if any couldn't be copied, in which
+/// case the sync isn't recorded so they're tried again next time
+fn push_saves(remote_dir: &Path, local_dir: &Path) -> Result<Vec<String>, String> {
+    let last_sync = last_sync(local_dir);
+    fs::create_dir_all(remote_dir)
+        .map_err(|error| format!("failed to create {}: {error}", remote_dir.display()))?;
+
+    let mut conflicts = Vec::new();
+    for name in SYNCED_FILES {
+        let (local, remote) = (local_dir.join(name), remote_dir.join(name));
+        if modified(&local).is_none_or(|local_modified| local_modified <= last_sync) {
+            continue;
+        }
+
+        // Don't overwrite changes made on another computer during the game
+        let target = match modified(&remote) {
+            Some(remote_modified) if remote_modified > last_sync => {
+                let conflict = remote_dir.join(format!("{name}.conflict-{}", now()));
+                conflicts.push(format!(
+                    "{name} was changed in the sync folder during the game, so this game's copy was saved to {} instead",
+                    conflict.display()
+                ));
+                conflict
+            }
+            _ => remote,
+        };
+        fs::copy(&local, target).map_err(|error| format!("failed to sync {name}: {error}"))?;
+    }
+
+    record_sync(local_dir)?;
+    Ok(conflicts)
+}
+
+/// Run a user's sync command through the shell
+fn run(command: &str) -> Result<(), String> {
+    let status = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).status()
+    } else {
+        Command::new("sh").args(["-c", command]).status()
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("sync command `{command}` failed with {status}")),
+        Err(error) => Err(format!("failed to run sync command `{command}`: {error}")),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// When the file was last modified, in seconds since the Unix epoch
+fn modified(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// When the saves were last synced, in seconds since the Unix epoch, or 0 if they never were
+fn last_sync(local_dir: &Path) -> u64 {
+    fs::read_to_string(local_dir.join(LAST_SYNC_FILE))
+        .ok()
+        .and_then(|contents| contents.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn record_sync(local_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(local_dir)
+        .and_then(|()| fs::write(local_dir.join(LAST_SYNC_FILE), now().to_string()))
+        .map_err(|error| format!("failed to record the sync time: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::{fs::File, time::Duration};
+
+    /// When the saves were last synced in the tests, in seconds since the Unix epoch
+    const LAST_SYNC: u64 = 1_000_000;
+
+    /// A local and a remote folder, last synced at `LAST_SYNC`
+    fn folders() -> (TempDir, TempDir) {
+        let (local, remote) = (TempDir::new(), TempDir::new());
+        fs::write(local.0.join(LAST_SYNC_FILE), LAST_SYNC.to_string())
+            .expect("Failed to write the sync time");
+        (local, remote)
+    }
+
+    /// Write a save, as if it was last changed before or after the last sync
+    fn write_save(path: &Path, contents: &str, changed_since_sync: bool) {
+        fs::write(path, contents).expect("Failed to write the save");
+        let modified = if changed_since_sync {
+            LAST_SYNC + 100
+        } else {
+            LAST_SYNC - 100
+        };
+        File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(UNIX_EPOCH + Duration::from_secs(modified)))
+            .expect("Failed to set the save's modified time");
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).expect("Failed to read the save")
+    }
+
+    /// The names of the files in the folder starting with the prefix
+    fn files_starting_with(directory: &Path, prefix: &str) -> Vec<String> {
+        fs::read_dir(directory)
+            .expect("Failed to read the folder")
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(prefix))
+            .collect()
+    }
+
+    #[test]
+    fn pull_copies_saves_only_changed_remotely() {
+        let (local, remote) = folders();
+        write_save(&local.0.join("pace_history.csv"), "local", false);
+        write_save(&remote.0.join("pace_history.csv"), "remote", true);
+
+        assert!(pull_saves(&remote.0, &local.0).is_empty());
+        assert_eq!(read(&local.0.join("pace_history.csv")), "remote");
+    }
+
+    #[test]
+    fn pull_keeps_both_copies_of_saves_changed_in_both_places() {
+        let (local, remote) = folders();
+        write_save(&local.0.join("pace_history.csv"), "local", true);
+        write_save(&remote.0.join("pace_history.csv"), "remote", true);
+
+        assert_eq!(pull_saves(&remote.0, &local.0).len(), 1);
+        assert_eq!(read(&local.0.join("pace_history.csv")), "local");
+        let conflicts = files_starting_with(&local.0, "pace_history.csv.conflict-");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(read(&local.0.join(&conflicts[0])), "remote");
+    }
+
+    #[test]
+    fn push_copies_saves_only_changed_locally() {
+        let (local, remote) = folders();
+        write_save(&local.0.join("dig_history.csv"), "local", true);
+        write_save(&remote.0.join("dig_history.csv"), "remote", false);
+
+        assert_eq!(push_saves(&remote.0, &local.0), Ok(Vec::new()));
+        assert_eq!(read(&remote.0.join("dig_history.csv")), "local");
+        assert!(last_sync(&local.0) > LAST_SYNC);
+    }
+
+    #[test]
+    fn push_reports_saves_changed_remotely_during_the_game() {
+        let (local, remote) = folders();
+        write_save(&local.0.join("dig_history.csv"), "local", true);
+        write_save(&remote.0.join("dig_history.csv"), "remote", true);
+
+        let conflicts = push_saves(&remote.0, &local.0).expect("The push failed");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(read(&remote.0.join("dig_history.csv")), "remote");
+        let kept = files_starting_with(&remote.0, "dig_history.csv.conflict-");
+        assert_eq!(kept.len(), 1);
+        assert_eq!(read(&remote.0.join(&kept[0])), "local");
+    }
+}
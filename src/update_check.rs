@@ -0,0 +1,97 @@
+use crate::config::Config;
+use serde::Deserialize;
+use std::{
+    fs,
+    sync::OnceLock,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/renpenguin/console-tetris/releases/latest";
+/// How long the latest release is remembered for before asking again
+const CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+/// The file in the config directory remembering when the last check was and what it found
+const CACHE_FILE: &str = "update_check";
+
+/// The latest release, once it's known to be newer than this one
+static NEWER_VERSION: OnceLock<String> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// Look for a newer release in the background, so a slow connection never holds up the game.
+/// Only asks GitHub at most once a day
+pub fn spawn() {
+    thread::spawn(|| {
+        if let Some(latest) = latest_release().filter(|latest| is_newer(latest)) {
+            let _ = NEWER_VERSION.set(latest);
+        }
+    });
+}
+
+/// The latest release, if it's newer than this one and the check has finished
+pub fn newer_version() -> Option<&'static str> {
+    NEWER_VERSION.get().map(String::as_str)
+}
+
+fn latest_release() -> Option<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let cache_path = Config::directory()?.join(CACHE_FILE);
+
+    // The cache holds the time of the last check and the release it found, e.g. `1700000000,v0.3.0`.
+    // The release is left empty if no check has succeeded yet
+    let cached = fs::read_to_string(&cache_path).ok().and_then(|contents| {
+        let (checked_at, tag) = contents.trim().split_once(',')?;
+        Some((checked_at.parse::<u64>().ok()?, String::from(tag)))
+    });
+    let previous_tag = cached
+        .as_ref()
+        .map(|(_, tag)| tag.clone())
+        .filter(|tag| !tag.is_empty());
+    if let Some((checked_at, _)) = cached {
+        if now.saturating_sub(checked_at) < CHECK_INTERVAL_SECS {
+            return previous_tag;
+        }
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into();
+    let release: Option<Release> = agent
+        .get(RELEASES_URL)
+        .header("User-Agent", "console-tetris")
+        .call()
+        .ok()
+        .and_then(|mut response| response.body_mut().read_json().ok());
+
+    // Failed checks are recorded too, so being offline or rate limited doesn't make every start
+    // wait on another request
+    let tag = release.map(|release| release.tag_name).or(previous_tag);
+    let _ = fs::write(
+        cache_path,
+        format!("{now},{}", tag.as_deref().unwrap_or_default()),
+    );
+    tag
+}
+
+/// Whether a release tag like `v0.3.0` is a later version than the one running
+fn is_newer(tag: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+
+    match (parse(tag), parse(env!("CARGO_PKG_VERSION"))) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
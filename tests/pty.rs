@@ -0,0 +1,234 @@
+//! End-to-end tests that run the game inside a pseudo-terminal, type at it and read back what it
+//! drew
+//!
+//! Snapshots of the screen are kept as golden files in `tests/snapshots`
+
+mod support;
+
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::{
+    env,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use support::{assert_golden, TempDir};
+
+const ROWS: u16 = 24;
+const COLUMNS: u16 = 80;
+/// How long to wait for the game to draw something before failing
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// The width of the board, with its walls, drawn at double width
+const BOARD_WIDTH: u16 = 24;
+/// The height of the board, with its floor
+const BOARD_HEIGHT: u16 = 21;
+
+/// The game running in a pseudo-terminal, with everything it has drawn parsed into a screen
+struct Session {
+    child: Box<dyn Child + Send + Sync>,
+    writer: Box<dyn Write + Send>,
+    screen: Arc<Mutex<vt100::Parser>>,
+    // Kept so the terminal stays open until the session ends
+    _master: Box<dyn MasterPty + Send>,
+    _config_dir: TempDir,
+}
+
+impl Session {
+    /// Start the game with the arguments and an empty config directory, so the player's own
+    /// settings don't get in the way
+    fn start(args: &[&str]) -> Self {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: ROWS,
+                cols: COLUMNS,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .expect("Failed to open a pseudo-terminal");
+
+        let config_dir = TempDir::new();
+        let mut command = CommandBuilder::new(env!("CARGO_BIN_EXE_tetris"));
+        command.args(args);
+        command.env("XDG_CONFIG_HOME", &config_dir.0);
+        command.env("APPDATA", &config_dir.0);
+        command.env("TERM", "xterm-256color");
+        for multiplexer_var in ["TMUX", "STY"] {
+            command.env_remove(multiplexer_var);
+        }
+
+        let child = pair
+            .slave
+            .spawn_command(command)
+            .expect("Failed to start the game");
+        let writer = pair.master.take_writer().expect("Failed to open input");
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .expect("Failed to open output");
+
+        let screen = Arc::new(Mutex::new(vt100::Parser::new(ROWS, COLUMNS, 0)));
+        let parser = Arc::clone(&screen);
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = reader.read(&mut buffer) {
+                if let Ok(mut parser) = parser.lock() {
+                    parser.process(&buffer[..read]);
+                }
+            }
+        });
+
+        Self {
+            child,
+            writer,
+            screen,
+            _master: pair.master,
+            _config_dir: config_dir,
+        }
+    }
+
+    /// Type the keys into the terminal
+    fn send(&mut self, keys: &str) {
+        self.writer
+            .write_all(keys.as_bytes())
+            .expect("Failed to type into the game");
+        self.writer.flush().expect("Failed to type into the game");
+    }
+
+    /// The text on screen, one line per row
+    fn contents(&self) -> String {
+        self.screen
+            .lock()
+            .expect("Screen lock poisoned")
+            .screen()
+            .contents()
+    }
+
+    /// The text in a rectangle of the screen, one line per row
+    fn region(&self, width: u16, height: u16) -> String {
+        let parser = self.screen.lock().expect("Screen lock poisoned");
+        (0..height)
+            .map(|row| {
+                parser
+                    .screen()
+                    .contents_between(row, 0, row, width)
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Wait until the screen shows the text, failing the test if it never does
+    fn wait_for(&self, text: &str) -> String {
+        let start = Instant::now();
+        loop {
+            let contents = self.contents();
+            if contents.contains(text) {
+                return contents;
+            }
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "`{text}` never appeared on screen:\n{contents}"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Wait for the game to exit, returning whether it was successful
+    fn wait_for_exit(&mut self) -> bool {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().expect("Failed to check the game") {
+                return status.success();
+            }
+            assert!(start.elapsed() < TIMEOUT, "The game never exited");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[test]
+fn timer_runs_without_input() {
+    let session = Session::start(&[]);
+
+    // The game loop has to keep running while it waits for input
+    session.wait_for("Time: 00:01");
+}
+
+#[test]
+fn hard_drops_match_snapshot() {
+    // Seeded so the same pieces are dealt every time, and slowed down so they don't fall any
+    // further while the test is typing
+    let mut session = Session::start(&["--mode", "practice", "--seed", "1", "--speed", "0.25"]);
+    // Seeded games show the sequence hash before starting
+    session.wait_for("Sequence hash");
+    session.send("\r");
+    session.wait_for("Score: 0");
+
+    for _ in 0..3 {
+        session.send(" ");
+        thread::sleep(Duration::from_millis(200));
+    }
+    // Each hard drop scores a point for every cell the piece fell
+    session.wait_for("Score: 53 |");
+
+    assert_golden(
+        "snapshots",
+        "hard_drops",
+        &session.region(BOARD_WIDTH, BOARD_HEIGHT),
+    );
+}
+
+#[test]
+fn replay_matches_recording() {
+    let log_dir = TempDir::new();
+    let log = log_dir.0.join("game.log");
+    let log = log
+        .to_str()
+        .expect("The temporary directory isn't valid UTF-8");
+    let mut session = Session::start(&["--mode", "practice", "--speed", "0.25", "--record", log]);
+    session.wait_for("Sequence hash");
+    session.send("\r");
+    session.wait_for("Score: 0");
+    // Left arrow, hard drop, right arrow twice and hard drop again
+    for keys in ["\x1b[D", " ", "\x1b[C", "\x1b[C", " "] {
+        session.send(keys);
+        thread::sleep(Duration::from_millis(200));
+    }
+    let score = session
+        .contents()
+        .lines()
+        .find_map(|line| line.split("Score: ").nth(1))
+        .and_then(|rest| rest.split(' ').next())
+        .map(String::from)
+        .expect("The score isn't on screen");
+    assert_ne!(score, "0", "The hard drops didn't score");
+    session.send("\x03");
+    assert!(session.wait_for_exit(), "The game didn't exit cleanly");
+
+    // The replay should be played at the recorded speed without being told it
+    let mut replay = Session::start(&["--mode", "practice", "--replay", log]);
+    replay.wait_for("Sequence hash");
+    replay.send("\r");
+    replay.wait_for(&format!("Score: {score} |"));
+}
+
+#[test]
+fn pause_and_quit() {
+    let mut session = Session::start(&[]);
+    session.wait_for("Score: 0");
+
+    session.send("\x1b");
+    session.wait_for("Paused");
+
+    // Ctrl+C
+    session.send("\x03");
+    assert!(session.wait_for_exit(), "The game didn't exit cleanly");
+}
@@ -0,0 +1,66 @@
+//! Helpers shared by the end-to-end tests and the game's own unit tests, which include this file
+//! by its path
+//!
+//! Golden files are kept in folders under `tests`. `UPDATE_SNAPSHOTS=1` records them, e.g. after
+//! an intended change to how the game looks. A missing golden file fails its test, so a fresh
+//! checkout can't pass without checking anything
+
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Compare the text against the golden file `tests/<directory>/<name>.txt`, or record it as the
+/// golden file if `UPDATE_SNAPSHOTS` is set
+pub fn assert_golden(directory: &str, name: &str, actual: &str) {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(directory);
+    let path = directory.join(format!("{name}.txt"));
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(&directory).expect("Failed to create the golden file directory");
+        fs::write(&path, actual).expect("Failed to write the golden file");
+        eprintln!("Recorded the golden file at {}", path.display());
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|error| {
+        panic!(
+            "Failed to read {}: {error}. Run the tests with UPDATE_SNAPSHOTS=1 to record it",
+            path.display()
+        )
+    });
+    assert_eq!(
+        expected,
+        actual,
+        "The output doesn't match {}",
+        path.display()
+    );
+}
+
+/// A directory that is deleted once the test is done with it
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "console-tetris-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).expect("Failed to create a temporary directory");
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}